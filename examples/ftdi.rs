@@ -0,0 +1,41 @@
+//! Bench bring-up through an FT232H
+//!
+//! Connect AD0 (SCL) and AD1 + AD2 (SDA) to the LM3549 with pull-ups. Run with
+//! `cargo run --example ftdi --features ftdi`.
+
+extern crate ftdi_embedded_hal as ftdi;
+extern crate lm3549;
+
+use ftdi::libftd2xx::Ft232h;
+use ftdi::{Delay, FtHal};
+use lm3549::{Bank, BankCurrents, LM3549};
+
+fn main() {
+    let device = Ft232h::with_description("Single RS232-HS").unwrap();
+    let hal = FtHal::init_freq(device, 400_000).unwrap();
+    let mut delay = Delay::new();
+
+    // Probe
+    let mut lm3549 = match LM3549::new_after_power_on(hal.i2c().unwrap(), &mut delay) {
+        Ok(dev) => dev,
+        Err((_, e)) => panic!("LM3549 not found: {:?}", e),
+    };
+
+    // Dump
+    println!("{}", lm3549.dump_registers().unwrap());
+
+    // Configure bank
+    let white = BankCurrents {
+        r: 0x100,
+        g: 0x100,
+        b: 0x100,
+    };
+    lm3549.write_bank_currents(Bank::B0, white).unwrap();
+    lm3549.select_bank(Bank::B0).unwrap();
+
+    // Fade
+    lm3549.set_fader_auto(0).unwrap();
+    lm3549.fade_to(255, 4, &mut delay).unwrap();
+
+    println!("Fault: {:?}", lm3549.get_fault().unwrap());
+}
@@ -0,0 +1,32 @@
+//! Bench bring-up through an Excamera I2CDriver
+//!
+//! Run with `cargo run --example i2cdriver --features i2cdriver -- /dev/ttyUSB0`. Prints the
+//! register file and the decoded faults.
+
+extern crate i2cdriver;
+extern crate lm3549;
+
+use i2cdriver::I2CDriver;
+use lm3549::{FaultKind, LM3549};
+
+fn main() {
+    let port = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "/dev/ttyUSB0".into());
+    let i2c = I2CDriver::open(&port).unwrap();
+    let mut lm3549 = LM3549::new(i2c);
+
+    println!("{}", lm3549.dump_registers().unwrap());
+
+    let fault = lm3549.get_fault().unwrap();
+    let mask = lm3549.get_fault_mask().unwrap();
+    println!("Fault: {:?}", fault);
+    for &kind in FaultKind::BY_PRIORITY.iter() {
+        if fault.has(kind) {
+            println!("  {:?} active, drives FAULT: {}", kind, mask.has(kind));
+        }
+    }
+    println!("  shorted driver: {:?}", fault.primary_short());
+    println!("  open driver: {:?}", fault.primary_open());
+    println!("FAULT asserted: {}", fault.asserts_pin(mask));
+}
@@ -0,0 +1,61 @@
+//! Two LM3549 at different addresses and another device sharing one embedded-hal 1.0 bus through `RefCellDevice`
+//!
+//! Run with `cargo run --example shared_bus --features eh1`.
+
+extern crate lm3549;
+
+use core::cell::RefCell;
+
+use embedded_hal_1::i2c::{ErrorType, I2c, Operation};
+use embedded_hal_bus::i2c::RefCellDevice;
+use lm3549::{Bank, BankCurrents, LM3549};
+
+/// Bus printing every transaction
+struct LoggingBus;
+
+impl ErrorType for LoggingBus {
+    type Error = core::convert::Infallible;
+}
+
+impl I2c for LoggingBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        for op in operations {
+            match op {
+                Operation::Write(bytes) => println!("{:#04x} write {:02x?}", address, bytes),
+                Operation::Read(buf) => {
+                    buf.iter_mut().for_each(|b| *b = 0);
+                    println!("{:#04x} read {} bytes", address, buf.len())
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let bus = RefCell::new(LoggingBus);
+
+    let mut front = LM3549::new_eh1(RefCellDevice::new(&bus));
+    let mut back = LM3549::new_eh1(RefCellDevice::new(&bus));
+    back.set_address(0x37).unwrap();
+    let mut other = RefCellDevice::new(&bus);
+
+    let red = BankCurrents {
+        r: 0x3FF,
+        ..Default::default()
+    };
+    let blue = BankCurrents {
+        b: 0x3FF,
+        ..Default::default()
+    };
+
+    front.write_bank_currents(Bank::B0, red).unwrap();
+    other.write(0x50, &[0x00, 0xAA]).unwrap();
+    back.write_bank_currents(Bank::B0, blue).unwrap();
+    front.select_bank(Bank::B0).unwrap();
+    back.select_bank(Bank::B0).unwrap();
+}
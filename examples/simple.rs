@@ -6,4 +6,8 @@ use lm3549::LM3549;
 
 fn main() {
     let i2c_bus = I2cdev::new("/dev/i2c-1").unwrap();
+    let mut lm3549 = LM3549::new(i2c_bus);
+
+    let fade = lm3549.read_effective_fade().unwrap();
+    println!("Fader: {}", fade);
 }
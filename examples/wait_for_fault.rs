@@ -0,0 +1,65 @@
+//! Wait for the FAULT output in an Embassy task
+//!
+//! On a target the pin is an `embassy_*::gpio::ExtiInput` or similar. Here the task runs on
+//! the std executor of `embassy-executor` and the pin reports a fault right away.
+
+extern crate embedded_hal_mock as mock;
+extern crate lm3549;
+
+use core::convert::Infallible;
+
+use embassy_executor::{Executor, Spawner};
+use embedded_hal_1::digital::ErrorType;
+use embedded_hal_async::digital::Wait;
+use lm3549::LM3549;
+use mock::i2c::{Mock, Transaction};
+
+/// FAULT output that is already asserted
+struct FaultPin;
+
+impl ErrorType for FaultPin {
+    type Error = Infallible;
+}
+
+impl Wait for FaultPin {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+#[embassy_executor::task]
+async fn fault_task() {
+    // Temperature shutdown reported in the fault register
+    let expectations = [
+        Transaction::write(0x36, vec![0x17]),
+        Transaction::read(0x36, vec![0x02]),
+    ];
+    let i2c = Mock::new(&expectations);
+    let mut lm3549 = LM3549::new(i2c);
+    let mut pin = FaultPin;
+
+    let fault = lm3549.wait_for_fault(&mut pin).await.unwrap();
+    println!("Fault: {:?}", fault);
+    std::process::exit(0);
+}
+
+fn main() {
+    let executor = Box::leak(Box::new(Executor::new()));
+    executor.run(|spawner: Spawner| spawner.spawn(fault_task()).unwrap());
+}
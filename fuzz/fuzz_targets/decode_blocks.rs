@@ -0,0 +1,36 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lm3549::{Color, Register, RegisterDump, MAX_CODE};
+
+fuzz_target!(|data: &[u8]| {
+    // Bank reads: codes stay within 10 bits and re-encode to the input without reserved bits
+    for chunk in data.chunks_exact(6) {
+        let mut buf = [0u8; 6];
+        buf.copy_from_slice(chunk);
+        let c = Color::from_le_bytes(buf);
+        assert!(c.r <= MAX_CODE && c.g <= MAX_CODE && c.b <= MAX_CODE);
+        let mut masked = buf;
+        for msb in masked.iter_mut().skip(1).step_by(2) {
+            *msb &= 0x03;
+        }
+        assert_eq!(c.to_le_bytes(), masked);
+    }
+
+    // Register dumps: every register reads back from its own address
+    if data.len() >= 0x1A {
+        let mut config = [0u8; 0x18];
+        config.copy_from_slice(&data[..0x18]);
+        let user = [data[0x18], data[0x19]];
+        let dump = RegisterDump::from_reads(config, user);
+        for r in Register::iter() {
+            let expected = match r as usize {
+                a @ 0x00..=0x17 => Some(config[a]),
+                a @ 0x19..=0x1A => Some(user[a - 0x19]),
+                _ => None,
+            };
+            assert_eq!(dump.get(r), expected);
+        }
+        assert_eq!(dump.0[0x18], 0);
+    }
+});
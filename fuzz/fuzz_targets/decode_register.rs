@@ -0,0 +1,39 @@
+#![no_main]
+
+use core::convert::TryFrom;
+
+use libfuzzer_sys::fuzz_target;
+use lm3549::{Bank, Ctrl, Fault, FaultKind, Ilimit, OpenShort, Register};
+
+fuzz_target!(|data: &[u8]| {
+    for &x in data {
+        // Addresses decode to the register at that address or not at all
+        if let Ok(r) = Register::try_from(x) {
+            assert_eq!(r as u8, x);
+        }
+        if let Some(b) = Bank::from_sel(x) {
+            assert_eq!(b.sel(), x);
+        }
+        let _ = OpenShort::from(x).channel();
+
+        // Re-encoding the decoded fields gives the input without reserved bits
+        let ctrl = Ctrl(x);
+        let mut c = Ctrl(0);
+        c.set_softstart(ctrl.softstart());
+        c.set_timeout(ctrl.timeout());
+        c.set_mfe(ctrl.mfe());
+        c.set_pwm(ctrl.pwm());
+        assert_eq!(c.0, x & 0x3F);
+
+        let ilimit = Ilimit(x);
+        let mut i = Ilimit(0);
+        i.set_pos_limit(ilimit.pos_limit());
+        i.set_neg_limit(ilimit.neg_limit());
+        assert_eq!(i.0, x & 0x33);
+
+        let fault = Fault(x);
+        assert_eq!(fault.is_err(), fault.highest_priority().is_some());
+        assert_eq!(fault.is_ok(), !fault.has_any(&FaultKind::BY_PRIORITY));
+        let _ = (fault.short(), fault.open(), fault.primary_short(), fault.primary_open());
+    }
+});
@@ -0,0 +1,72 @@
+#!/bin/sh
+# Check that every optional feature builds on its own, that a build with
+# `--no-default-features` contains none of the feature-gated tables and helpers, and that
+# bus tracing only costs space with `log` or `defmt` enabled.
+#
+# The check looks for item names and string data in the compiled rlib: generic driver
+# methods are only instantiated by the application, so their names are the only trace
+# they leave in the library.
+set -eu
+
+cd "$(dirname "$0")/.."
+
+GATED="names color-math effects eeprom"
+TRACE="log defmt"
+
+for feature in "" $GATED $TRACE; do
+    echo "checking --no-default-features --features '$feature'"
+    cargo check -q --no-default-features --features "$feature"
+done
+
+# feature:pattern pairs, each pattern only present with its feature enabled
+PATTERNS="
+names:BankSelIr0Lsb
+color-math:from_kelvin
+color-math:from_hsv
+effects:BREATHE_TABLE
+effects:FadeController
+eeprom:eeprom_store
+"
+
+target=$(mktemp -d)
+trap 'rm -rf "$target"' EXIT
+
+rlib() {
+    CARGO_TARGET_DIR="$target/$1" cargo build -q --release --lib $2
+    ls "$target/$1"/release/deps/liblm3549-*.rlib
+}
+
+full=$(rlib full "")
+min=$(rlib min --no-default-features)
+
+status=0
+for pair in $PATTERNS; do
+    feature=${pair%%:*}
+    pattern=${pair#*:}
+    if ! LC_ALL=C grep -q -a "$pattern" "$full"; then
+        echo "error: '$pattern' not found in the default build, update the check for '$feature'"
+        status=1
+    elif LC_ALL=C grep -q -a "$pattern" "$min"; then
+        echo "error: '$pattern' of feature '$feature' present without the feature"
+        status=1
+    fi
+done
+
+# Tracing is compiled out of the default build and in with either backend
+for feature in $TRACE; do
+    traced=$(rlib "$feature" "--features $feature")
+    if ! LC_ALL=C grep -q -a "lm3549: write" "$traced"; then
+        echo "error: bus tracing not found in the '$feature' build"
+        status=1
+    elif LC_ALL=C grep -q -a "lm3549: write" "$full"; then
+        echo "error: bus tracing present without '$feature'"
+        status=1
+    else
+        echo "'$feature' build: $(wc -c < "$traced") bytes rlib"
+    fi
+done
+
+if [ $status -eq 0 ]; then
+    echo "minimal build: $(wc -c < "$min") bytes rlib, default build: $(wc -c < "$full") bytes rlib"
+fi
+exit $status
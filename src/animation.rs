@@ -0,0 +1,273 @@
+//! Keyframe animation of bank colors
+
+use hal::blocking::i2c;
+
+use crate::{Bank, Color, Error, LM3549};
+
+/// Keyframes of `(color, duration_ms)`
+///
+/// Each keyframe fades linearly to the next over its duration. The last keyframe fades back
+/// to the first one when looping and is held otherwise. Zero-duration keyframes are skipped.
+#[derive(Copy, Clone, Debug)]
+pub struct Scene<'a> {
+    frames: &'a [(Color, u16)],
+}
+
+impl<'a> Scene<'a> {
+    /// Create a scene from keyframes
+    pub fn new(frames: &'a [(Color, u16)]) -> Self {
+        Scene { frames }
+    }
+
+    /// Total duration in milliseconds
+    pub fn duration(&self) -> u32 {
+        self.frames.iter().map(|&(_, d)| d as u32).sum()
+    }
+
+    /// Color at `t` milliseconds, `None` if the scene has no keyframe with a duration
+    pub fn sample(&self, t: u32, looping: bool) -> Option<Color> {
+        let total = self.duration();
+        if total == 0 {
+            return None;
+        }
+        let mut frames = self.frames.iter().filter(|&&(_, d)| d > 0);
+        let first = frames.clone().next().map(|&(c, _)| c);
+        let t = if looping { t % total } else { t };
+        let mut start = 0;
+        while let Some(&(from, d)) = frames.next() {
+            let d = d as u32;
+            if t < start + d {
+                let to = match frames.clone().next() {
+                    Some(&(c, _)) => c,
+                    None if looping => first?,
+                    None => from,
+                };
+                return Some(from.lerp(to, t - start, d));
+            }
+            start += d;
+        }
+        self.frames
+            .iter()
+            .rev()
+            .find(|&&(_, d)| d > 0)
+            .map(|&(c, _)| c)
+    }
+}
+
+/// Colors of a one-shot keyframe animation sampled every `tick_ms`
+///
+/// Yields the color at `0, tick_ms, 2 * tick_ms, ...` and ends with the last keyframe, also
+/// when the scene duration is not a multiple of the tick. Does no I2C, so the colors can be
+/// written from any scheduler.
+#[derive(Copy, Clone, Debug)]
+pub struct KeyframeIter<'a> {
+    scene: Scene<'a>,
+    tick_ms: u32,
+    t: u32,
+    done: bool,
+}
+
+impl<'a> KeyframeIter<'a> {
+    /// Create an iterator over keyframes, a zero tick is treated as 1 ms
+    pub fn new(frames: &'a [(Color, u16)], tick_ms: u16) -> Self {
+        KeyframeIter {
+            scene: Scene::new(frames),
+            tick_ms: tick_ms.max(1) as u32,
+            t: 0,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for KeyframeIter<'a> {
+    type Item = Color;
+
+    fn next(&mut self) -> Option<Color> {
+        if self.done {
+            return None;
+        }
+        let total = self.scene.duration();
+        let color = self.scene.sample(self.t.min(total), false);
+        self.done = color.is_none() || self.t >= total;
+        self.t = self.t.saturating_add(self.tick_ms);
+        color
+    }
+}
+
+/// Playback state of a [`Player`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayerState {
+    /// Scene is still playing
+    Playing,
+    /// One-shot scene has ended, the last keyframe is shown
+    Finished,
+}
+
+/// Plays a [`Scene`] on the driver
+///
+/// Colors are written to the bank not currently shown, which is then selected, so a
+/// partially written bank is never displayed.
+#[derive(Copy, Clone, Debug)]
+pub struct Player<'a> {
+    scene: Scene<'a>,
+    looping: bool,
+    elapsed: u32,
+    banks: [Bank; 2],
+    front: usize,
+}
+
+impl<'a> Player<'a> {
+    /// Create a player alternating between banks B0 and B1
+    pub fn new(scene: Scene<'a>, looping: bool) -> Self {
+        Player::with_banks(scene, looping, [Bank::B0, Bank::B1])
+    }
+
+    /// Create a player alternating between two given banks
+    pub fn with_banks(scene: Scene<'a>, looping: bool, banks: [Bank; 2]) -> Self {
+        Player {
+            scene,
+            looping,
+            elapsed: 0,
+            banks,
+            front: 0,
+        }
+    }
+
+    /// Restart the scene from its first keyframe
+    pub fn rewind(&mut self) {
+        self.elapsed = 0;
+    }
+
+    /// Advance by `elapsed_ms` and show the color at the new position
+    pub fn tick<I2C, E>(
+        &mut self,
+        driver: &mut LM3549<I2C>,
+        elapsed_ms: u16,
+    ) -> Result<PlayerState, Error<E>>
+    where
+        I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+    {
+        let total = self.scene.duration();
+        self.elapsed = self.elapsed.saturating_add(elapsed_ms as u32);
+        if self.looping && total > 0 {
+            self.elapsed %= total;
+        }
+        let color = match self.scene.sample(self.elapsed, self.looping) {
+            Some(c) => c,
+            None => return Ok(PlayerState::Finished),
+        };
+        let back = 1 - self.front;
+        driver.write_bank_color(self.banks[back], color)?;
+        driver.select_bank(self.banks[back])?;
+        self.front = back;
+        if !self.looping && self.elapsed >= total {
+            Ok(PlayerState::Finished)
+        } else {
+            Ok(PlayerState::Playing)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+    use crate::Register;
+
+    const BLACK: Color = Color::new(0, 0, 0);
+    const WHITE: Color = Color::new(100, 200, 400);
+    const HALF: Color = Color::new(50, 100, 200);
+
+    #[test]
+    fn scene_interpolates_between_keyframes() {
+        let frames = [(BLACK, 100), (WHITE, 100)];
+        let scene = Scene::new(&frames);
+        assert_eq!(scene.duration(), 200);
+        assert_eq!(scene.sample(0, false), Some(BLACK));
+        assert_eq!(scene.sample(50, false), Some(HALF));
+        assert_eq!(scene.sample(150, false), Some(WHITE));
+        assert_eq!(scene.sample(500, false), Some(WHITE));
+        assert_eq!(scene.sample(150, true), Some(HALF));
+        assert_eq!(scene.sample(250, true), Some(HALF));
+    }
+
+    #[test]
+    fn single_keyframe_scene_holds_its_color() {
+        let frames = [(WHITE, 100)];
+        let scene = Scene::new(&frames);
+        for &t in [0, 50, 99, 100, 1000].iter() {
+            assert_eq!(scene.sample(t, false), Some(WHITE));
+            assert_eq!(scene.sample(t, true), Some(WHITE));
+        }
+    }
+
+    #[test]
+    fn player_writes_inactive_bank_before_selecting_it() {
+        let (mut dev, model) = Model::driver();
+        let frames = [(BLACK, 100), (WHITE, 100)];
+        let mut player = Player::new(Scene::new(&frames), false);
+
+        player.tick(&mut dev, 50).unwrap();
+        player.tick(&mut dev, 50).unwrap();
+        let writes = model.writes();
+        assert_eq!(writes.len(), 4);
+        assert_eq!(writes[0][0], Bank::B1 as u8);
+        assert_eq!(writes[1], [Register::BankSel as u8, Bank::B1.sel()]);
+        assert_eq!(writes[2][0], Bank::B0 as u8);
+        assert_eq!(writes[3], [Register::BankSel as u8, Bank::B0.sel()]);
+        assert_eq!(dev.read_bank(Bank::B1).unwrap(), HALF);
+        assert_eq!(dev.read_bank(Bank::B0).unwrap(), WHITE);
+    }
+
+    #[test]
+    fn one_shot_finishes_and_looping_keeps_playing() {
+        let (mut dev, _) = Model::driver();
+        let frames = [(BLACK, 100), (WHITE, 100)];
+
+        let mut once = Player::new(Scene::new(&frames), false);
+        assert_eq!(once.tick(&mut dev, 150).unwrap(), PlayerState::Playing);
+        assert_eq!(once.tick(&mut dev, 100).unwrap(), PlayerState::Finished);
+        assert_eq!(dev.read_bank(Bank::B0).unwrap(), WHITE);
+
+        let mut looping = Player::new(Scene::new(&frames), true);
+        assert_eq!(looping.tick(&mut dev, 150).unwrap(), PlayerState::Playing);
+        assert_eq!(looping.tick(&mut dev, 100).unwrap(), PlayerState::Playing);
+        assert_eq!(dev.read_bank(Bank::B0).unwrap(), HALF);
+    }
+
+    #[test]
+    fn keyframes_of_empty_slice() {
+        let mut iter = KeyframeIter::new(&[], 10);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn keyframes_skip_zero_duration() {
+        let frames = [(BLACK, 100), (Color::new(0x3FF, 0, 0), 0), (WHITE, 100)];
+        let mut iter = KeyframeIter::new(&frames, 50);
+        assert!(iter.by_ref().take(3).eq([BLACK, HALF, WHITE]));
+        assert_eq!(iter.count(), 2);
+
+        let frames = [(WHITE, 0)];
+        assert_eq!(KeyframeIter::new(&frames, 50).next(), None);
+    }
+
+    #[test]
+    fn keyframes_end_on_last_keyframe_with_uneven_tick() {
+        let frames = [(BLACK, 90), (WHITE, 10)];
+        let colors = KeyframeIter::new(&frames, 40);
+        assert!(colors.eq([
+            BLACK,
+            BLACK.lerp(WHITE, 40u32, 90),
+            BLACK.lerp(WHITE, 80u32, 90),
+            WHITE,
+        ]));
+    }
+
+    #[test]
+    fn keyframes_zero_tick() {
+        let frames = [(BLACK, 3)];
+        assert_eq!(KeyframeIter::new(&frames, 0).count(), 4);
+    }
+}
@@ -0,0 +1,93 @@
+//! Waiting for faults on the FAULT output without polling the bus
+
+use embedded_hal_async::digital::Wait;
+use hal::blocking::i2c;
+
+use crate::{Error, Fault, LM3549};
+
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    /// Wait until the FAULT output goes low, then read the fault register
+    ///
+    /// Only faults enabled in the fault mask drive the output, see
+    /// [`LM3549::set_fault_mask`]. The register read itself is blocking.
+    pub async fn wait_for_fault<P: Wait>(&mut self, fault_pin: &mut P) -> Result<Fault, Error<E>> {
+        fault_pin.wait_for_low().await.map_err(|_| Error::Pin)?;
+        self.get_fault()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    use embedded_hal_1::digital::{ErrorKind, ErrorType};
+
+    use super::*;
+    use crate::testing::Model;
+    use crate::Register;
+
+    /// FAULT output that resolves every wait right away, or fails it
+    struct Pin(bool);
+
+    impl ErrorType for Pin {
+        type Error = ErrorKind;
+    }
+
+    impl Wait for Pin {
+        async fn wait_for_high(&mut self) -> Result<(), ErrorKind> {
+            unreachable!()
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), ErrorKind> {
+            if self.0 {
+                Ok(())
+            } else {
+                Err(ErrorKind::Other)
+            }
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), ErrorKind> {
+            unreachable!()
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), ErrorKind> {
+            unreachable!()
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), ErrorKind> {
+            unreachable!()
+        }
+    }
+
+    fn block_on<F: Future>(f: F) -> F::Output {
+        let mut f = pin!(f);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(out) = f.as_mut().poll(&mut cx) {
+                return out;
+            }
+        }
+    }
+
+    #[test]
+    fn wait_for_fault_reads_fault() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Fault, 0x22);
+        let fault = block_on(dev.wait_for_fault(&mut Pin(true))).unwrap();
+        assert!(fault.tsd());
+        assert_eq!(fault.short() as u8, 1);
+    }
+
+    #[test]
+    fn wait_for_fault_pin_error() {
+        let (mut dev, model) = Model::driver();
+        let res = block_on(dev.wait_for_fault(&mut Pin(false)));
+        assert!(matches!(res, Err(Error::Pin)));
+        assert!(model.ops().is_empty());
+    }
+}
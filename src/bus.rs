@@ -0,0 +1,445 @@
+//! Register access layer used by every driver method
+//!
+//! Bus errors are wrapped here with the [`Operation`] that failed, so every method reports
+//! the register it was accessing.
+
+use core::convert::TryFrom;
+use core::fmt;
+
+use hal::blocking::i2c;
+
+use crate::{Error, Register, LM3549};
+
+/// Kind of bus access that failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OpKind {
+    /// Register read
+    Read,
+    /// Single register write
+    Write,
+    /// Write of consecutive registers
+    BlockWrite,
+}
+
+/// Bus access that failed, carried by [`Error::I2c`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Operation {
+    /// First register accessed, `None` for an address without a register
+    pub register: Option<Register>,
+    /// Kind of access
+    pub kind: OpKind,
+}
+
+impl Operation {
+    pub(crate) fn new(address: u8, kind: OpKind) -> Self {
+        Operation {
+            register: Register::try_from(address).ok(),
+            kind,
+        }
+    }
+
+    /// Read starting at the fault register, which clears the latched faults
+    pub(crate) fn is_fault_read(&self) -> bool {
+        self.kind == OpKind::Read && self.register == Some(Register::Fault)
+    }
+}
+
+/// Describes the access, e.g. "block write of Ctrl (0x14)"
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            OpKind::Read => "read",
+            OpKind::Write => "write",
+            OpKind::BlockWrite => "block write",
+        };
+        match self.register {
+            Some(r) => write!(f, "{} of {} ({:#04x})", kind, r, r as u8),
+            None => f.write_str(kind),
+        }
+    }
+}
+
+/// Recovery of a stuck bus, e.g. by clocking SCL until SDA is released
+///
+/// Once `after` consecutive transactions have failed and `filter` accepts the last one,
+/// `recover` is called. If it returns `true` the failed transaction is retried once.
+///
+/// The bus error of an embedded-hal 0.2 bus carries no kind, so `filter` sees the failed
+/// [`Operation`]. Reads starting at [`Register::Fault`] are never retried, the failed read
+/// may already have cleared the latched faults.
+#[derive(Copy, Clone, Debug)]
+pub struct BusRecovery {
+    /// Consecutive failed transactions before recovering
+    pub after: u8,
+    /// Free the bus, returns whether that succeeded
+    pub recover: fn() -> bool,
+    /// Failed operations that may trigger a recovery, e.g. [`BusRecovery::any`]
+    pub filter: fn(&Operation) -> bool,
+}
+
+impl BusRecovery {
+    /// Filter accepting every operation
+    pub fn any(_: &Operation) -> bool {
+        true
+    }
+}
+
+impl<I2C> LM3549<I2C> {
+    /// Install a bus recovery hook, see [`BusRecovery`]
+    pub fn set_bus_recovery(&mut self, recovery: BusRecovery) {
+        self.recovery = Some(recovery);
+    }
+
+    /// Remove the bus recovery hook
+    pub fn clear_bus_recovery(&mut self) {
+        self.recovery = None;
+    }
+
+    /// Count a transaction result, `true` if the failed `op` should be retried
+    fn recover_after<T, E>(&mut self, res: &Result<T, E>, op: &Operation) -> bool {
+        if res.is_ok() {
+            self.bus_failures = 0;
+            return false;
+        }
+        self.bus_failures = self.bus_failures.saturating_add(1);
+        match self.recovery {
+            Some(r)
+                if self.bus_failures >= r.after
+                    && !op.is_fault_read()
+                    && (r.filter)(op)
+                    && (r.recover)() =>
+            {
+                self.bus_failures = 0;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    /// Write `buf`, a register address followed by the data for consecutive registers
+    pub(crate) fn bus_write(&mut self, buf: &[u8]) -> Result<(), Error<E>> {
+        let kind = if buf.len() > 2 {
+            OpKind::BlockWrite
+        } else {
+            OpKind::Write
+        };
+        let op = Operation::new(buf[0], kind);
+        let mut res = self.i2c.write(self.address, buf);
+        if self.recover_after(&res, &op) {
+            res = self.i2c.write(self.address, buf);
+        }
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        trace::write(buf, res.is_ok());
+        res.map_err(|e| Error::I2c(e, op))
+    }
+
+    /// Read consecutive registers starting at address `start`
+    pub(crate) fn bus_read(&mut self, start: u8, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let op = Operation::new(start, OpKind::Read);
+        let mut res = self.read_once(start, buf);
+        if self.recover_after(&res, &op) {
+            res = self.read_once(start, buf);
+        }
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        trace::read(start, buf, res.is_ok());
+        res.map_err(|e| Error::I2c(e, op))
+    }
+
+    fn read_once(&mut self, start: u8, buf: &mut [u8]) -> Result<(), E> {
+        self.i2c.write(self.address, &[start])?;
+        self.i2c.read(self.address, buf)
+    }
+}
+
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Transactional<Error = E>,
+{
+    /// Read consecutive registers starting at address `start` in one transaction
+    pub(crate) fn bus_read_tx(&mut self, start: u8, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let op = Operation::new(start, OpKind::Read);
+        let mut res = self.exec_once(start, buf);
+        if self.recover_after(&res, &op) {
+            res = self.exec_once(start, buf);
+        }
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        trace::read(start, buf, res.is_ok());
+        res.map_err(|e| Error::I2c(e, op))
+    }
+
+    fn exec_once(&mut self, start: u8, buf: &mut [u8]) -> Result<(), E> {
+        let mut ops = [i2c::Operation::Write(&[start]), i2c::Operation::Read(buf)];
+        self.i2c.exec(self.address, &mut ops)
+    }
+}
+
+/// Bus activity tracing to `log` and `defmt`, compiled out unless one of them is enabled
+#[cfg(any(feature = "log", feature = "defmt"))]
+pub(crate) mod trace {
+    use core::convert::TryFrom;
+    #[cfg(feature = "log")]
+    use core::fmt;
+
+    use crate::Register;
+
+    /// Register name of an address, the address itself if it is not a register
+    struct Name(u8);
+
+    #[cfg(feature = "log")]
+    impl fmt::Display for Name {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match Register::try_from(self.0) {
+                Ok(r) => write!(f, "{}", r),
+                Err(a) => write!(f, "{:#04x}", a),
+            }
+        }
+    }
+
+    #[cfg(feature = "defmt")]
+    impl defmt::Format for Name {
+        fn format(&self, f: defmt::Formatter) {
+            match Register::try_from(self.0) {
+                Ok(r) => defmt::write!(f, "{}", r),
+                Err(a) => defmt::write!(f, "{=u8:#04x}", a),
+            }
+        }
+    }
+
+    pub(crate) fn write(buf: &[u8], ok: bool) {
+        let (start, data) = match buf.split_first() {
+            Some((start, data)) => (Name(*start), data),
+            None => return,
+        };
+        if ok {
+            #[cfg(feature = "log")]
+            log::trace!("lm3549: write {} <- {:02x?}", start, data);
+            #[cfg(feature = "defmt")]
+            defmt::trace!("lm3549: write {} <- {=[u8]:02x}", start, data);
+        } else {
+            #[cfg(feature = "log")]
+            log::debug!("lm3549: write {} failed", start);
+            #[cfg(feature = "defmt")]
+            defmt::debug!("lm3549: write {} failed", start);
+        }
+    }
+
+    pub(crate) fn read(start: u8, buf: &[u8], ok: bool) {
+        let start = Name(start);
+        if ok {
+            #[cfg(feature = "log")]
+            log::trace!("lm3549: read {} -> {:02x?}", start, buf);
+            #[cfg(feature = "defmt")]
+            defmt::trace!("lm3549: read {} -> {=[u8]:02x}", start, buf);
+        } else {
+            #[cfg(feature = "log")]
+            log::debug!("lm3549: read {} failed", start);
+            #[cfg(feature = "defmt")]
+            defmt::debug!("lm3549: read {} failed", start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::testing::Model;
+
+    static RETRIED: AtomicUsize = AtomicUsize::new(0);
+    static FILTERED: AtomicUsize = AtomicUsize::new(0);
+    static FAULT: AtomicUsize = AtomicUsize::new(0);
+    static TX: AtomicUsize = AtomicUsize::new(0);
+
+    fn retried() -> bool {
+        RETRIED.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    fn filtered() -> bool {
+        FILTERED.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    fn fault() -> bool {
+        FAULT.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    fn tx() -> bool {
+        TX.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    #[test]
+    fn recovery_retries_after_consecutive_failures() {
+        let (mut dev, model) = Model::driver();
+        dev.set_bus_recovery(BusRecovery {
+            after: 2,
+            recover: retried,
+            filter: BusRecovery::any,
+        });
+        model.fail_in(0);
+        model.fail_in(1);
+        assert!(dev.set_fader(0x10).is_err());
+        assert_eq!(RETRIED.load(Ordering::SeqCst), 0);
+        dev.set_fader(0x20).unwrap();
+        assert_eq!(RETRIED.load(Ordering::SeqCst), 1);
+        assert_eq!(model.reg(Register::Fader), 0x20);
+        assert_eq!(model.writes().len(), 3);
+    }
+
+    #[test]
+    fn recovery_respects_filter() {
+        let (mut dev, model) = Model::driver();
+        dev.set_bus_recovery(BusRecovery {
+            after: 1,
+            recover: filtered,
+            filter: |op| op.kind == OpKind::Read,
+        });
+        model.fail_in(0);
+        assert!(matches!(
+            dev.set_fader(0x10),
+            Err(Error::I2c(_, op)) if op.register == Some(Register::Fader)
+        ));
+        assert_eq!(FILTERED.load(Ordering::SeqCst), 0);
+        model.fail_in(1);
+        assert_eq!(dev.read(Register::Fader).unwrap(), 0);
+        assert_eq!(FILTERED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn recovery_never_retries_fault_reads() {
+        let (mut dev, model) = Model::driver();
+        dev.set_bus_recovery(BusRecovery {
+            after: 1,
+            recover: fault,
+            filter: BusRecovery::any,
+        });
+        model.fail_in(1);
+        assert!(dev.get_fault().is_err());
+        assert_eq!(FAULT.load(Ordering::SeqCst), 0);
+        assert_eq!(model.ops().len(), 2);
+    }
+
+    #[test]
+    fn transactional_reads_use_recovery() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Ctrl, 0x12);
+        dev.set_bus_recovery(BusRecovery {
+            after: 1,
+            recover: tx,
+            filter: BusRecovery::any,
+        });
+        model.fail_in(1);
+        assert_eq!(dev.read_tx(Register::Ctrl).unwrap(), 0x12);
+        assert_eq!(TX.load(Ordering::SeqCst), 1);
+
+        dev.clear_bus_recovery();
+        model.fail_in(1);
+        assert!(matches!(
+            dev.read_tx(Register::Ctrl),
+            Err(Error::I2c(_, op)) if op == Operation::new(Register::Ctrl as u8, OpKind::Read)
+        ));
+        assert_eq!(dev.bus_failures, 1);
+        dev.read_tx(Register::Ctrl).unwrap();
+        assert_eq!(dev.bus_failures, 0);
+    }
+
+    #[cfg(feature = "log")]
+    mod capture {
+        extern crate std;
+
+        use std::cell::RefCell;
+        use std::string::{String, ToString};
+        use std::sync::Once;
+        use std::vec::Vec;
+
+        std::thread_local! {
+            static RECORDS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+        }
+
+        /// Keeps the records of each test thread apart, tests run in parallel
+        struct Capture;
+
+        impl log::Log for Capture {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                let line = record.args().to_string();
+                RECORDS.with(|r| r.borrow_mut().push(line));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static CAPTURE: Capture = Capture;
+        static INIT: Once = Once::new();
+
+        /// Start capturing records on this thread, dropping earlier ones
+        pub fn start() {
+            INIT.call_once(|| {
+                log::set_logger(&CAPTURE).unwrap();
+                log::set_max_level(log::LevelFilter::Trace);
+            });
+            RECORDS.with(|r| r.borrow_mut().clear());
+        }
+
+        /// Records captured on this thread since `start`
+        pub fn take() -> Vec<String> {
+            RECORDS.with(|r| r.borrow_mut().split_off(0))
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "log")]
+    fn bus_access_is_logged_once_with_register_and_data() {
+        let (mut dev, model) = Model::driver();
+        capture::start();
+        dev.write(Register::Fader, 0x42).unwrap();
+        assert_eq!(capture::take(), ["lm3549: write Fader <- [42]"]);
+
+        model.set_reg(Register::Ctrl, 0x0c);
+        dev.read(Register::Ctrl).unwrap();
+        assert_eq!(capture::take(), ["lm3549: read Ctrl -> [0c]"]);
+
+        model.fail_in(0);
+        assert!(dev.write(Register::Fader, 0x43).is_err());
+        assert_eq!(capture::take(), ["lm3549: write Fader failed"]);
+    }
+
+    #[test]
+    fn errors_carry_the_failed_operation() {
+        let (mut dev, model) = Model::driver();
+        model.fail_in(0);
+        match dev.set_ctrl(crate::Ctrl::default()) {
+            Err(Error::I2c(_, op)) => assert_eq!(
+                op,
+                Operation {
+                    register: Some(Register::Ctrl),
+                    kind: OpKind::Write,
+                }
+            ),
+            _ => panic!("expected a bus error"),
+        }
+
+        model.fail_in(0);
+        match dev.read_bank(crate::Bank::B1) {
+            Err(Error::I2c(_, op)) => assert_eq!(
+                op,
+                Operation {
+                    register: Some(Register::Ir1Lsb),
+                    kind: OpKind::Read,
+                }
+            ),
+            _ => panic!("expected a bus error"),
+        }
+    }
+}
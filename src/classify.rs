@@ -0,0 +1,416 @@
+//! Classification of bus errors for retries and diagnostics
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c;
+
+use crate::{Error, LM3549};
+
+/// What a failed operation says about the device
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Device did not acknowledge its address, e.g. an unpowered or unplugged board
+    DeviceMissing,
+    /// Failure that may not happen again, e.g. lost arbitration or a glitch on the bus
+    Transient,
+    /// Failure that will happen again, e.g. an invalid argument
+    Permanent,
+}
+
+/// Bus errors that know their [`ErrorClass`]
+///
+/// Implemented for every embedded-hal 1.0 I2C error with the `eh1` feature. Errors of
+/// embedded-hal 0.2 buses carry no kind, classify them with [`Error::classify_with`] or
+/// implement this trait for the bus error type.
+pub trait Classify {
+    /// Class of the error
+    fn classify(&self) -> ErrorClass;
+}
+
+#[cfg(feature = "eh1")]
+impl<T: embedded_hal_1::i2c::Error> Classify for T {
+    fn classify(&self) -> ErrorClass {
+        use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        match self.kind() {
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => ErrorClass::DeviceMissing,
+            ErrorKind::NoAcknowledge(_)
+            | ErrorKind::ArbitrationLoss
+            | ErrorKind::Bus
+            | ErrorKind::Overrun => ErrorClass::Transient,
+            _ => ErrorClass::Permanent,
+        }
+    }
+}
+
+/// State of the bus behind an embedded-hal 1.0 I2C error
+#[cfg(feature = "eh1")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BusCondition {
+    /// Nothing acknowledged the address
+    Absent,
+    /// Another master or a glitch disturbed the transfer, retrying may help
+    BusContended,
+    /// Anything else
+    Other,
+}
+
+/// Condition of the bus behind an embedded-hal 1.0 I2C error
+#[cfg(feature = "eh1")]
+pub fn bus_condition<E: embedded_hal_1::i2c::Error>(e: &E) -> BusCondition {
+    use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    match e.kind() {
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => BusCondition::Absent,
+        ErrorKind::ArbitrationLoss | ErrorKind::Bus => BusCondition::BusContended,
+        _ => BusCondition::Other,
+    }
+}
+
+impl<E> Error<E> {
+    /// Class of the error, using `classify` for bus errors
+    ///
+    /// Timeouts are transient, all other driver errors are permanent.
+    pub fn classify_with(&self, classify: impl FnOnce(&E) -> ErrorClass) -> ErrorClass {
+        match self {
+            Error::I2c(e, _) | Error::PartialWrite { source: e, .. } => classify(e),
+            Error::Timeout => ErrorClass::Transient,
+            _ => ErrorClass::Permanent,
+        }
+    }
+}
+
+impl<E: Classify> Error<E> {
+    /// Class of the error
+    pub fn class(&self) -> ErrorClass {
+        self.classify_with(Classify::classify)
+    }
+
+    /// Device did not acknowledge its address
+    pub fn is_device_missing(&self) -> bool {
+        self.class() == ErrorClass::DeviceMissing
+    }
+
+    /// Operation may succeed when retried
+    pub fn is_transient(&self) -> bool {
+        self.class() == ErrorClass::Transient
+    }
+
+    /// Operation will fail again when retried
+    pub fn is_permanent(&self) -> bool {
+        self.class() == ErrorClass::Permanent
+    }
+
+    /// Transient and not a failed fault register read
+    ///
+    /// The read clears latched faults, so a read that failed on the bus may already have
+    /// cleared what a retry would report.
+    fn is_retryable(&self) -> bool {
+        match self {
+            Error::I2c(_, op) if op.is_fault_read() => false,
+            _ => self.is_transient(),
+        }
+    }
+}
+
+/// Retries with exponential backoff, see [`LM3549::retry_with_backoff`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one
+    pub attempts: u8,
+    /// Wait before the first retry in microseconds
+    pub initial_backoff_us: u16,
+    /// Factor the wait grows by after every retry
+    pub multiplier: u16,
+    /// Longest wait between two attempts in microseconds
+    pub max_backoff_us: u16,
+    /// Longest total wait of all retries in microseconds
+    pub max_total_us: u32,
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry`, counting from 0, saturating at `max_backoff_us`
+    pub fn backoff_us(&self, retry: u8) -> u16 {
+        let mut us = self.initial_backoff_us.min(self.max_backoff_us);
+        for _ in 0..retry {
+            us = us.saturating_mul(self.multiplier).min(self.max_backoff_us);
+        }
+        us
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, waiting 100 µs and then 200 µs, at most 10 ms in total
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            initial_backoff_us: 100,
+            multiplier: 2,
+            max_backoff_us: 5_000,
+            max_total_us: 10_000,
+        }
+    }
+}
+
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+    E: Classify,
+{
+    /// Run `f` up to `attempts` times while it fails with a transient error
+    ///
+    /// A failed read of the fault register is never retried, the read may already have
+    /// cleared the latched faults.
+    pub fn retry_transient<R>(
+        &mut self,
+        attempts: u8,
+        mut f: impl FnMut(&mut Self) -> Result<R, Error<E>>,
+    ) -> Result<R, Error<E>> {
+        let mut tries = 1;
+        loop {
+            match f(self) {
+                Err(e) if e.is_retryable() && tries < attempts => tries += 1,
+                res => return res,
+            }
+        }
+    }
+
+    /// Run `f` while it fails with a transient error, waiting between attempts per `policy`
+    ///
+    /// Stops early rather than exceeding `policy.max_total_us` of waiting. Like
+    /// [`LM3549::retry_transient`] a failed read of the fault register is never retried.
+    pub fn retry_with_backoff<R, D: DelayUs<u16>>(
+        &mut self,
+        policy: &RetryPolicy,
+        delay: &mut D,
+        mut f: impl FnMut(&mut Self) -> Result<R, Error<E>>,
+    ) -> Result<R, Error<E>> {
+        let mut retry = 0;
+        let mut waited_us: u32 = 0;
+        loop {
+            let err = match f(self) {
+                Err(e) if e.is_retryable() => e,
+                res => return res,
+            };
+            let backoff = policy.backoff_us(retry);
+            if retry + 1 >= policy.attempts || waited_us + backoff as u32 > policy.max_total_us {
+                return Err(err);
+            }
+            delay.delay_us(backoff);
+            waited_us += backoff as u32;
+            retry += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+    use crate::{Bank, Channel, OpKind, Operation, Register};
+
+    fn op() -> Operation {
+        Operation::new(Register::Fader as u8, OpKind::Write)
+    }
+
+    #[test]
+    fn driver_errors_classify() {
+        let table: [(Error<ErrorClass>, ErrorClass); 8] = [
+            (
+                Error::I2c(ErrorClass::DeviceMissing, op()),
+                ErrorClass::DeviceMissing,
+            ),
+            (
+                Error::I2c(ErrorClass::Transient, op()),
+                ErrorClass::Transient,
+            ),
+            (
+                Error::PartialWrite {
+                    written: 1,
+                    source: ErrorClass::Transient,
+                    operation: op(),
+                },
+                ErrorClass::Transient,
+            ),
+            (Error::Timeout, ErrorClass::Transient),
+            (Error::InvalidRange, ErrorClass::Permanent),
+            (Error::BlockTooLong, ErrorClass::Permanent),
+            (Error::UnknownPreset(3), ErrorClass::Permanent),
+            (
+                Error::CodeOutOfRange {
+                    bank: Bank::B0,
+                    channel: Channel::Red,
+                    code: 0x400,
+                },
+                ErrorClass::Permanent,
+            ),
+        ];
+        for (e, class) in table.iter() {
+            assert_eq!(e.classify_with(|&c| c), *class, "{:?}", e);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "eh1")]
+    fn eh1_error_kinds_classify() {
+        use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        let table = [
+            (
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+                ErrorClass::DeviceMissing,
+                BusCondition::Absent,
+            ),
+            (
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+                ErrorClass::Transient,
+                BusCondition::Other,
+            ),
+            (
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+                ErrorClass::Transient,
+                BusCondition::Other,
+            ),
+            (
+                ErrorKind::ArbitrationLoss,
+                ErrorClass::Transient,
+                BusCondition::BusContended,
+            ),
+            (
+                ErrorKind::Bus,
+                ErrorClass::Transient,
+                BusCondition::BusContended,
+            ),
+            (
+                ErrorKind::Overrun,
+                ErrorClass::Transient,
+                BusCondition::Other,
+            ),
+            (ErrorKind::Other, ErrorClass::Permanent, BusCondition::Other),
+        ];
+        for &(kind, class, condition) in table.iter() {
+            assert_eq!(kind.classify(), class, "{:?}", kind);
+            assert_eq!(bus_condition(&kind), condition, "{:?}", kind);
+            assert_eq!(Error::I2c(kind, op()).class(), class, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn retry_transient_retries_up_to_the_limit() {
+        let (mut dev, model) = Model::driver();
+        model.fail_in(0);
+        model.fail_in(1);
+        dev.retry_transient(3, |dev| dev.write(Register::Fader, 0x10))
+            .unwrap();
+        assert_eq!(model.ops().len(), 3);
+        assert_eq!(model.reg(Register::Fader), 0x10);
+
+        model.clear_ops();
+        model.fail_in(0);
+        model.fail_in(1);
+        assert!(dev
+            .retry_transient(2, |dev| dev.write(Register::Fader, 0x20))
+            .unwrap_err()
+            .is_transient());
+        assert_eq!(model.ops().len(), 2);
+        assert_eq!(model.reg(Register::Fader), 0x10);
+    }
+
+    #[test]
+    fn retry_transient_gives_up_on_permanent_errors() {
+        let (mut dev, model) = Model::driver();
+        model.0.borrow_mut().error_class = ErrorClass::Permanent;
+        model.fail_in(0);
+        assert!(dev
+            .retry_transient(3, |dev| dev.write(Register::Fader, 0x10))
+            .unwrap_err()
+            .is_permanent());
+        assert_eq!(model.ops().len(), 1);
+    }
+
+    #[test]
+    fn retry_transient_never_retries_fault_reads() {
+        let (mut dev, model) = Model::driver();
+        model.fail_in(1);
+        assert!(matches!(
+            dev.retry_transient(3, |dev| dev.read(Register::Fault)),
+            Err(Error::I2c(_, op)) if op.is_fault_read()
+        ));
+        assert_eq!(model.ops().len(), 2);
+
+        model.clear_ops();
+        model.fail_in(1);
+        assert_eq!(
+            dev.retry_transient(3, |dev| dev.read(Register::Ctrl))
+                .unwrap(),
+            0
+        );
+        assert_eq!(model.ops().len(), 4);
+    }
+
+    /// Delay recording every wait
+    #[derive(Default)]
+    struct Delays {
+        us: [u16; 8],
+        len: usize,
+    }
+
+    impl DelayUs<u16> for Delays {
+        fn delay_us(&mut self, us: u16) {
+            self.us[self.len] = us;
+            self.len += 1;
+        }
+    }
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        attempts: 5,
+        initial_backoff_us: 100,
+        multiplier: 3,
+        max_backoff_us: 500,
+        max_total_us: 10_000,
+    };
+
+    #[test]
+    fn retry_with_backoff_grows_up_to_the_cap() {
+        let (mut dev, model) = Model::driver();
+        for n in 0..4 {
+            model.fail_in(n);
+        }
+        let mut delay = Delays::default();
+        dev.retry_with_backoff(&POLICY, &mut delay, |dev| dev.write(Register::Fader, 0x10))
+            .unwrap();
+        assert_eq!(delay.us[..delay.len], [100, 300, 500, 500]);
+        assert_eq!(model.ops().len(), 5);
+        assert_eq!(model.reg(Register::Fader), 0x10);
+    }
+
+    #[test]
+    fn retry_with_backoff_stops_at_the_total_wait() {
+        let (mut dev, model) = Model::driver();
+        for n in 0..5 {
+            model.fail_in(n);
+        }
+        let policy = RetryPolicy {
+            max_total_us: 1_000,
+            ..POLICY
+        };
+        let mut delay = Delays::default();
+        assert!(dev
+            .retry_with_backoff(&policy, &mut delay, |dev| dev.write(Register::Fader, 0x10))
+            .is_err());
+        assert_eq!(delay.us[..delay.len], [100, 300, 500]);
+        assert_eq!(model.ops().len(), 4);
+    }
+
+    #[test]
+    fn retry_with_backoff_gives_up_on_permanent_errors() {
+        let (mut dev, model) = Model::driver();
+        model.0.borrow_mut().error_class = ErrorClass::Permanent;
+        model.fail_in(0);
+        let mut delay = Delays::default();
+        assert!(dev
+            .retry_with_backoff(&POLICY, &mut delay, |dev| dev.write(Register::Fader, 0x10))
+            .is_err());
+        assert_eq!(delay.len, 0);
+        assert_eq!(model.ops().len(), 1);
+    }
+}
@@ -0,0 +1,511 @@
+/// Largest current code accepted by the 10-bit current registers
+pub const MAX_CODE: u16 = 0x3FF;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::Channel;
+
+/// LED driver current per code step in microamps
+///
+/// The datasheet specifies 700 mA at full-scale code 0x3FF for every LED driver (LM3549
+/// datasheet, *Electrical Characteristics*, LED current). All milliamp conversions in this
+/// crate go through this constant.
+pub const CURRENT_LSB_UA: u32 = 684;
+
+/// LED driver current at full-scale code [`MAX_CODE`] in milliamps
+pub const FULL_SCALE_MA: u16 = 700;
+
+/// Current in milliamps for a code, rounded to the nearest milliamp
+pub const fn code_to_ma(code: u16) -> u16 {
+    ((code as u32 * CURRENT_LSB_UA + 500) / 1000) as u16
+}
+
+/// Largest code not exceeding `ma` milliamps, saturating at [`MAX_CODE`]
+pub const fn ma_to_code(ma: u16) -> u16 {
+    let code = ma as u32 * 1000 / CURRENT_LSB_UA;
+    if code > MAX_CODE as u32 {
+        MAX_CODE
+    } else {
+        code as u16
+    }
+}
+
+/// Expand an 8-bit level to a 10-bit code, mapping 0xFF to 0x3FF
+pub const fn expand8(v: u8) -> u16 {
+    ((v as u16) << 2) | ((v as u16) >> 6)
+}
+
+/// Red, green and blue driver current codes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    /// Red driver current code
+    pub r: u16,
+    /// Green driver current code
+    pub g: u16,
+    /// Blue driver current code
+    pub b: u16,
+}
+
+/// Encode a code as its LSB/MSB register pair, the MSB holding the top 2 bits
+pub(crate) fn encode_code(buf: &mut [u8], code: u16) {
+    LittleEndian::write_u16(buf, code & MAX_CODE);
+}
+
+/// Decode a code from its LSB/MSB register pair, ignoring unused MSB bits
+pub(crate) fn decode_code(buf: &[u8]) -> u16 {
+    LittleEndian::read_u16(buf) & MAX_CODE
+}
+
+/// Bank current settings, usable in const items
+///
+/// ```
+/// const BOOT_COLOR: lm3549::BankCurrents = lm3549::BankCurrents::new(0x200, 0x180, 0x0F0);
+/// ```
+pub type BankCurrents = Color;
+
+impl Color {
+    /// Create a color from current codes, `None` if a code does not fit in 10 bits
+    pub const fn try_new(r: u16, g: u16, b: u16) -> Option<Self> {
+        if r <= MAX_CODE && g <= MAX_CODE && b <= MAX_CODE {
+            Some(Color { r, g, b })
+        } else {
+            None
+        }
+    }
+
+    /// Create a color from current codes
+    ///
+    /// Panics if a code does not fit in 10 bits, which fails the build in a const context.
+    pub const fn new(r: u16, g: u16, b: u16) -> Self {
+        assert!(r <= MAX_CODE && g <= MAX_CODE && b <= MAX_CODE);
+        Color { r, g, b }
+    }
+
+    /// Create a color from currents in milliamps
+    ///
+    /// Panics if a current exceeds [`FULL_SCALE_MA`], which fails the build in a const context.
+    pub const fn from_ma(r: u16, g: u16, b: u16) -> Self {
+        assert!(r <= FULL_SCALE_MA && g <= FULL_SCALE_MA && b <= FULL_SCALE_MA);
+        Color {
+            r: ma_to_code(r),
+            g: ma_to_code(g),
+            b: ma_to_code(b),
+        }
+    }
+
+    /// Create a color from 8-bit levels, see [`expand8`]
+    pub const fn from_rgb8(r: u8, g: u8, b: u8) -> Self {
+        Color {
+            r: expand8(r),
+            g: expand8(g),
+            b: expand8(b),
+        }
+    }
+
+    /// Copy with every channel clamped to the 10-bit range
+    pub fn clamped(&self) -> Self {
+        Color {
+            r: self.r.min(MAX_CODE),
+            g: self.g.min(MAX_CODE),
+            b: self.b.min(MAX_CODE),
+        }
+    }
+
+    /// Code of a channel
+    pub fn get(&self, channel: Channel) -> u16 {
+        match channel {
+            Channel::Red => self.r,
+            Channel::Green => self.g,
+            Channel::Blue => self.b,
+        }
+    }
+
+    /// Set the code of a channel
+    pub fn set(&mut self, channel: Channel, code: u16) {
+        match channel {
+            Channel::Red => self.r = code,
+            Channel::Green => self.g = code,
+            Channel::Blue => self.b = code,
+        }
+    }
+
+    /// Linear interpolation, `num / den` of the way from `self` to `to`
+    pub fn lerp<N: Into<u32>>(&self, to: Color, num: N, den: N) -> Color {
+        let (num, den) = (num.into() as i64, den.into().max(1) as i64);
+        let mix = |a: u16, b: u16| (a as i64 + (b as i64 - a as i64) * num / den) as u16;
+        Color {
+            r: mix(self.r, to.r),
+            g: mix(self.g, to.g),
+            b: mix(self.b, to.b),
+        }
+    }
+
+    /// Decode the red, green and blue register pairs of a bank
+    pub fn from_le_bytes(buf: [u8; 6]) -> Self {
+        Color {
+            r: decode_code(&buf[0..2]),
+            g: decode_code(&buf[2..4]),
+            b: decode_code(&buf[4..6]),
+        }
+    }
+
+    /// Encode as the red, green and blue register pairs of a bank
+    pub fn to_le_bytes(&self) -> [u8; 6] {
+        let mut buf = [0u8; 6];
+        encode_code(&mut buf[0..2], self.r);
+        encode_code(&mut buf[2..4], self.g);
+        encode_code(&mut buf[4..6], self.b);
+        buf
+    }
+
+    /// Sum of the three channel codes
+    pub fn total(&self) -> u32 {
+        self.r as u32 + self.g as u32 + self.b as u32
+    }
+
+    /// Sum of the three channel currents in milliamps
+    pub fn total_ma(&self) -> u32 {
+        (self.total() * CURRENT_LSB_UA + 500) / 1000
+    }
+}
+
+impl From<(u16, u16, u16)> for Color {
+    fn from((r, g, b): (u16, u16, u16)) -> Self {
+        Color { r, g, b }
+    }
+}
+
+impl From<Color> for (u16, u16, u16) {
+    fn from(c: Color) -> Self {
+        (c.r, c.g, c.b)
+    }
+}
+
+#[cfg(feature = "rgb")]
+impl From<rgb::RGB8> for Color {
+    fn from(c: rgb::RGB8) -> Self {
+        Color {
+            r: expand8(c.r),
+            g: expand8(c.g),
+            b: expand8(c.b),
+        }
+    }
+}
+
+#[cfg(feature = "rgb")]
+impl From<rgb::RGB16> for Color {
+    fn from(c: rgb::RGB16) -> Self {
+        Color {
+            r: c.r >> 6,
+            g: c.g >> 6,
+            b: c.b >> 6,
+        }
+    }
+}
+
+#[cfg(feature = "rgb")]
+impl From<Color> for rgb::RGB8 {
+    fn from(c: Color) -> Self {
+        let c = c.clamped();
+        rgb::RGB8::new((c.r >> 2) as u8, (c.g >> 2) as u8, (c.b >> 2) as u8)
+    }
+}
+
+#[cfg(feature = "rgb")]
+impl From<Color> for rgb::RGB16 {
+    fn from(c: Color) -> Self {
+        let c = c.clamped();
+        let widen = |v: u16| (v << 6) | (v >> 4);
+        rgb::RGB16::new(widen(c.r), widen(c.g), widen(c.b))
+    }
+}
+
+/// Linear sRGB components are clamped to `0.0..=1.0` and scaled to the 10-bit code range,
+/// as LED light output is proportional to its current.
+#[cfg(feature = "palette")]
+impl From<palette::LinSrgb<f32>> for Color {
+    fn from(c: palette::LinSrgb<f32>) -> Self {
+        let code = |v: f32| (v.clamp(0.0, 1.0) * MAX_CODE as f32 + 0.5) as u16;
+        Color {
+            r: code(c.red),
+            g: code(c.green),
+            b: code(c.blue),
+        }
+    }
+}
+
+/// Gamma-encoded sRGB is linearized before scaling to current codes.
+#[cfg(feature = "palette")]
+impl From<palette::Srgb<u8>> for Color {
+    fn from(c: palette::Srgb<u8>) -> Self {
+        let linear: palette::LinSrgb<f32> = c.into_format::<f32>().into_linear();
+        Color::from(linear)
+    }
+}
+
+/// Number of hue steps around the color wheel, 256 per sector
+#[cfg(feature = "color-math")]
+pub const HUE_RANGE: u16 = 6 * 256;
+
+#[cfg(feature = "color-math")]
+impl Color {
+    /// Color from hue (`0..HUE_RANGE`, wrapping), saturation and value using integer math
+    pub fn from_hsv(hue: u16, sat: u8, val: u8) -> Self {
+        let h = (hue % HUE_RANGE) as u32;
+        let (s, f) = (sat as u32, h % 256);
+        let v = val as u32 * MAX_CODE as u32 / 255;
+        let p = v * (255 - s) / 255;
+        let q = v * (255 - s * f / 255) / 255;
+        let t = v * (255 - s * (255 - f) / 255) / 255;
+        let (r, g, b) = match h / 256 {
+            0 => (v, t, p),
+            1 => (q, v, p),
+            2 => (p, v, t),
+            3 => (p, q, v),
+            4 => (t, p, v),
+            _ => (v, p, q),
+        };
+        Color {
+            r: r as u16,
+            g: g as u16,
+            b: b as u16,
+        }
+    }
+}
+
+/// Endless sweep around the color wheel at constant saturation and value
+///
+/// Holds no references, so it can live in a `static` driven from an interrupt.
+#[cfg(feature = "color-math")]
+#[derive(Copy, Clone, Debug)]
+pub struct HueSweep {
+    hue: u16,
+    step: u16,
+    sat: u8,
+    val: u8,
+}
+
+#[cfg(feature = "color-math")]
+impl HueSweep {
+    /// Create a sweep starting at red, advancing `step` hue units per color
+    pub const fn new(step: u16, sat: u8, val: u8) -> Self {
+        HueSweep {
+            hue: 0,
+            step,
+            sat,
+            val,
+        }
+    }
+}
+
+#[cfg(feature = "color-math")]
+impl Iterator for HueSweep {
+    type Item = Color;
+
+    fn next(&mut self) -> Option<Color> {
+        let color = Color::from_hsv(self.hue, self.sat, self.val);
+        self.hue = ((self.hue as u32 + self.step as u32) % HUE_RANGE as u32) as u16;
+        Some(color)
+    }
+}
+
+/// Approximate blackbody colors as 8-bit levels, every 500 K from 2000 K to 6500 K
+#[cfg(feature = "color-math")]
+const KELVIN_TABLE: [(u8, u8, u8); 10] = [
+    (255, 137, 14),
+    (255, 161, 72),
+    (255, 180, 107),
+    (255, 196, 137),
+    (255, 209, 163),
+    (255, 219, 186),
+    (255, 228, 206),
+    (255, 236, 224),
+    (255, 243, 239),
+    (255, 249, 253),
+];
+
+#[cfg(feature = "color-math")]
+impl Color {
+    /// Approximate a white of color temperature `kelvin` at brightness `level`
+    ///
+    /// Interpolates linearly in a blackbody table spaced 500 K apart and clamps to
+    /// 2000..=6500 K. The result assumes LEDs of equal efficacy per channel, so it is a
+    /// starting point for calibration rather than an accurate color point.
+    pub fn from_kelvin(kelvin: u16, level: u8) -> Self {
+        let k = kelvin.clamp(2000, 6500) as u32 - 2000;
+        let i = (k / 500) as usize;
+        let frac = k % 500;
+        let lo = KELVIN_TABLE[i];
+        let hi = KELVIN_TABLE[(i + 1).min(KELVIN_TABLE.len() - 1)];
+        let mix = |a: u8, b: u8| {
+            let v = (a as u32 * (500 - frac) + b as u32 * frac) / 500;
+            (expand8(v as u8) as u32 * level as u32 / 255) as u16
+        };
+        Color {
+            r: mix(lo.0, hi.0),
+            g: mix(lo.1, hi.1),
+            b: mix(lo.2, hi.2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOT: BankCurrents = BankCurrents::new(0x200, 0x180, 0x0F0);
+    const MAX: Option<BankCurrents> = BankCurrents::try_new(MAX_CODE, MAX_CODE, MAX_CODE);
+    const OVER: Option<BankCurrents> = BankCurrents::try_new(0, 0x400, 0);
+
+    #[test]
+    fn const_bank_currents() {
+        assert_eq!((BOOT.r, BOOT.g, BOOT.b), (0x200, 0x180, 0x0F0));
+        assert!(MAX.is_some());
+        assert!(OVER.is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_panics_at_run_time() {
+        let g = core::hint::black_box(0x400);
+        BankCurrents::new(0, g, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "color-math")]
+    fn from_kelvin_warm_to_cool() {
+        let warm = Color::from_kelvin(2700, 255);
+        let neutral = Color::from_kelvin(4000, 255);
+        let cool = Color::from_kelvin(6500, 255);
+        assert_eq!((warm.r, neutral.r, cool.r), (MAX_CODE, MAX_CODE, MAX_CODE));
+        assert!(warm.g < neutral.g && neutral.g < cool.g);
+        assert!(warm.b < neutral.b && neutral.b < cool.b);
+        assert!(warm.b < warm.g);
+    }
+
+    #[test]
+    #[cfg(feature = "color-math")]
+    fn from_kelvin_clamps_and_scales() {
+        assert_eq!(Color::from_kelvin(0, 255), Color::from_kelvin(2000, 255));
+        assert_eq!(
+            Color::from_kelvin(u16::MAX, 255),
+            Color::from_kelvin(6500, 255)
+        );
+        assert_eq!(
+            Color::from_kelvin(2000, 255),
+            Color::new(MAX_CODE, expand8(137), expand8(14))
+        );
+        assert_eq!(
+            Color::from_kelvin(6500, 255),
+            Color::new(MAX_CODE, expand8(249), expand8(253))
+        );
+        assert_eq!(Color::from_kelvin(4000, 0), Color::new(0, 0, 0));
+        let half = Color::from_kelvin(6500, 128);
+        assert_eq!(half.r, (MAX_CODE as u32 * 128 / 255) as u16);
+    }
+
+    #[test]
+    fn code_ma_round_trips() {
+        assert_eq!(code_to_ma(0), 0);
+        assert_eq!(ma_to_code(0), 0);
+        assert_eq!(code_to_ma(1), 1);
+        assert_eq!(ma_to_code(1), 1);
+        assert_eq!(code_to_ma(MAX_CODE), 700);
+        assert_eq!(ma_to_code(700), MAX_CODE);
+        assert_eq!(ma_to_code(u16::MAX), MAX_CODE);
+        for code in [0, 1, 2, 0x100, 0x200, MAX_CODE] {
+            let back = ma_to_code(code_to_ma(code));
+            assert!(
+                (code as i32 - back as i32).abs() <= 1,
+                "{} -> {}",
+                code,
+                back
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rgb")]
+    fn rgb_endpoints() {
+        assert_eq!(Color::from(rgb::RGB8::new(0, 0, 0)), Color::new(0, 0, 0));
+        assert_eq!(
+            Color::from(rgb::RGB8::new(255, 255, 255)),
+            Color::new(MAX_CODE, MAX_CODE, MAX_CODE)
+        );
+        assert_eq!(expand8(255), MAX_CODE);
+        assert_eq!(
+            Color::from(rgb::RGB8::new(0x80, 0x01, 0xFE)),
+            Color::new(expand8(0x80), expand8(0x01), expand8(0xFE))
+        );
+        assert_eq!(
+            Color::from(rgb::RGB16::new(0, 0xFFFF, 0x8000)),
+            Color::new(0, MAX_CODE, 0x200)
+        );
+
+        for v in [0u8, 1, 0x80, 255] {
+            let c = Color::from(rgb::RGB8::new(v, v, v));
+            assert_eq!(rgb::RGB8::from(c), rgb::RGB8::new(v, v, v));
+        }
+        assert_eq!(
+            rgb::RGB16::from(Color {
+                r: 0,
+                g: MAX_CODE,
+                b: 0xFFFF,
+            }),
+            rgb::RGB16::new(0, 0xFFFF, 0xFFFF)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "palette")]
+    fn palette_endpoints_and_clamping() {
+        let lin = |r, g, b| Color::from(palette::LinSrgb::new(r, g, b));
+        assert_eq!(lin(0.0, 0.0, 0.0), Color::new(0, 0, 0));
+        assert_eq!(lin(1.0, 1.0, 1.0), Color::new(MAX_CODE, MAX_CODE, MAX_CODE));
+        assert_eq!(lin(-0.5, 2.0, 0.5), Color::new(0, MAX_CODE, 512));
+
+        let srgb = |r, g, b| Color::from(palette::Srgb::new(r, g, b));
+        assert_eq!(srgb(0, 0, 0), Color::new(0, 0, 0));
+        assert_eq!(
+            srgb(255, 255, 255),
+            Color::new(MAX_CODE, MAX_CODE, MAX_CODE)
+        );
+        // Mid-grey sRGB is about a fifth of the linear light
+        let grey = srgb(128, 128, 128);
+        assert!((215..=225).contains(&grey.r), "{}", grey.r);
+    }
+
+    #[test]
+    #[cfg(feature = "color-math")]
+    fn hue_sweep_wraps_around() {
+        let hsv = |hue| Color::from_hsv(hue, 255, 255);
+        assert!(HueSweep::new(500, 255, 255).take(5).eq([
+            hsv(0),
+            hsv(500),
+            hsv(1000),
+            hsv(1500),
+            hsv(2000 - HUE_RANGE),
+        ]));
+        assert!(HueSweep::new(HUE_RANGE, 255, 255)
+            .take(3)
+            .all(|c| c == hsv(0)));
+        let mut sweep = HueSweep::new(u16::MAX, 255, 255);
+        sweep.next();
+        assert_eq!(sweep.next(), Some(hsv(u16::MAX % HUE_RANGE)));
+    }
+
+    #[test]
+    fn tuple_conversions_and_clamping() {
+        let c = Color::from((0x100, 0x3FF, 0x400));
+        assert_eq!((c.r, c.g, c.b), (0x100, 0x3FF, 0x400));
+        assert_eq!(<(u16, u16, u16)>::from(c), (0x100, 0x3FF, 0x400));
+        assert_eq!(c.clamped(), Color::new(0x100, MAX_CODE, MAX_CODE));
+        assert_eq!(
+            Color::from((u16::MAX, 0, 0x401)).clamped(),
+            Color::new(MAX_CODE, 0, MAX_CODE)
+        );
+        assert_eq!(Color::new(1, 2, 3).clamped(), Color::new(1, 2, 3));
+        assert_eq!(Color::try_new(0x400, 0, 0), None);
+    }
+}
@@ -0,0 +1,371 @@
+//! Complete device configuration
+
+use hal::blocking::delay::DelayMs;
+use hal::blocking::i2c;
+
+use crate::{Bank, Color, Ctrl, Error, FaultMask, Ilimit, Register, CONFIG_LEN, LM3549};
+
+/// Value kept in User2 by [`LM3549::reinitialize`] to detect a device reset
+///
+/// User2 powers on as zero, see [`LM3549::detect_device_reset`]. User1 holds the preset id
+/// of [`LM3549::set_preset_id`], so the sentinel cannot go there.
+pub const RESET_SENTINEL: u8 = 0xA5;
+
+/// Power-on values of the configuration registers, BankSel to FaultMask
+///
+/// Banks are off, the fader is fully open and the current limit is positive 1000 mA and
+/// negative 1100 mA; everything else is zero. Matches [`Config::default`].
+pub const DEFAULTS: [(Register, u8); CONFIG_LEN] = {
+    let mut table = [(Register::BankSel, 0u8); CONFIG_LEN];
+    let mut i = 0;
+    while i < CONFIG_LEN {
+        table[i] = (Register::ALL[i], 0x00);
+        i += 1;
+    }
+    table[Register::Fader as usize].1 = 0xFF;
+    table[Register::Ilimit as usize].1 = 0x11;
+    table
+};
+
+/// Contents of the configuration registers, BankSel to FaultMask
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// Selected bank
+    pub bank: Bank,
+    /// Currents of banks B0, B1 and B2
+    pub banks: [Color; 3],
+    /// Master fader level
+    pub fader: u8,
+    /// Control register
+    pub ctrl: Ctrl,
+    /// Current limit register
+    pub ilimit: Ilimit,
+    /// Fault mask register
+    pub fault_mask: FaultMask,
+}
+
+impl Default for Config {
+    /// Bank 0 selected, all banks off, fader fully open and default control, current limit
+    /// and fault mask registers
+    fn default() -> Self {
+        Config {
+            bank: Bank::B0,
+            banks: [Color::default(); 3],
+            fader: 0xFF,
+            ctrl: Ctrl::default(),
+            ilimit: Ilimit::default(),
+            fault_mask: FaultMask::default(),
+        }
+    }
+}
+
+impl<I2C> LM3549<I2C> {
+    /// Install the presets selected by [`LM3549::apply_preset_id`], as `(id, config)` pairs
+    pub fn set_presets(&mut self, presets: &'static [(u8, Config)]) {
+        self.presets = presets;
+    }
+
+    /// Install the configuration [`LM3549::health_check`] compares the device against
+    pub fn set_golden_config(&mut self, config: Config) {
+        self.golden = Some(config);
+    }
+
+    /// Remove the golden configuration
+    pub fn clear_golden_config(&mut self) {
+        self.golden = None;
+    }
+
+    /// Encode a configuration as the register block from BankSel to FaultMask
+    ///
+    /// The banks go through the range policy and channel order like any bank write.
+    pub(crate) fn encode_config<E>(&self, config: &Config) -> Result<[u8; CONFIG_LEN], Error<E>> {
+        let mut data = [0u8; CONFIG_LEN];
+        data[Register::BankSel as usize] = config.bank.sel();
+        data[Register::Ir0Lsb as usize..=Register::Ib2Msb as usize]
+            .copy_from_slice(&self.limit_banks(&config.banks)?);
+        data[Register::Fader as usize] = config.fader;
+        data[Register::Ctrl as usize] = config.ctrl.0;
+        data[Register::Ilimit as usize] = config.ilimit.0;
+        data[Register::FaultMask as usize] = config.fault_mask.0;
+        Ok(data)
+    }
+}
+
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    /// Write a complete configuration in one transaction
+    ///
+    /// BankSel (0x00), the bank currents (0x01 to 0x12) and Fader, Ctrl, Ilimit and
+    /// FaultMask (0x13 to 0x16) are one contiguous block, so the whole configuration is a
+    /// single block write. All banks are checked against the
+    /// [`RangePolicy`](crate::RangePolicy) before anything is written.
+    pub fn apply(&mut self, config: &Config) -> Result<(), Error<E>> {
+        let data = self.encode_config(config)?;
+        self.write_block(Register::BankSel, &data)
+    }
+
+    /// Write a configuration like [`LM3549::apply`] and wait out its soft-start time
+    ///
+    /// Blocks for [`SoftStart::as_millis`](crate::SoftStart::as_millis) of the soft start
+    /// in `config.ctrl`, so the outputs have finished ramping when this returns.
+    pub fn power_on_blocking<D: DelayMs<u16>>(
+        &mut self,
+        config: &Config,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        self.apply(config)?;
+        delay.delay_ms(config.ctrl.softstart().as_millis());
+        Ok(())
+    }
+
+    /// Store a preset id in User1, which keeps its value over a reset
+    pub fn set_preset_id(&mut self, id: u8) -> Result<(), Error<E>> {
+        self.write(Register::User1, id)
+    }
+
+    /// Preset id stored in User1
+    pub fn get_preset_id(&mut self) -> Result<u8, Error<E>> {
+        self.read(Register::User1)
+    }
+
+    /// Apply the preset `id` from the preset table and store its id in User1
+    pub fn apply_preset_id(&mut self, id: u8) -> Result<(), Error<E>> {
+        let config = match self.presets.iter().find(|(i, _)| *i == id) {
+            Some((_, config)) => *config,
+            None => return Err(Error::UnknownPreset(id)),
+        };
+        self.apply(&config)?;
+        self.set_preset_id(id)
+    }
+
+    /// Write the power-on [`DEFAULTS`] to the configuration registers
+    pub fn reset_to_defaults(&mut self) -> Result<(), Error<E>> {
+        let mut data = [0u8; CONFIG_LEN];
+        for (d, &(_, value)) in data.iter_mut().zip(DEFAULTS.iter()) {
+            *d = value;
+        }
+        self.write_block(Register::BankSel, &data)
+    }
+
+    /// First configuration register differing from its power-on default, with its value
+    ///
+    /// The fault, user and EEPROM registers are not checked.
+    pub fn first_non_default(&mut self) -> Result<Option<(Register, u8)>, Error<E>> {
+        let mut actual = [0u8; CONFIG_LEN];
+        self.read_registers(Register::BankSel, &mut actual)?;
+        Ok(DEFAULTS
+            .iter()
+            .zip(actual.iter())
+            .find(|((_, default), actual)| default != *actual)
+            .map(|(&(register, _), &actual)| (register, actual)))
+    }
+
+    /// Whether all configuration registers hold their power-on defaults
+    pub fn is_default_config(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.first_non_default()?.is_none())
+    }
+
+    /// Whether the device was reset since the last [`LM3549::reinitialize`]
+    ///
+    /// Checks User2 for the [`RESET_SENTINEL`], so User2 must be left to the driver. Catches
+    /// any reset of the device alone, e.g. a supply glitch the MCU survived.
+    pub fn detect_device_reset(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read(Register::User2)? != RESET_SENTINEL)
+    }
+
+    /// Apply `config` to a device in an unknown state and store the [`RESET_SENTINEL`]
+    ///
+    /// Use it for the initial configuration as well as after [`LM3549::detect_device_reset`].
+    /// Everything the driver knows about the device, like the last fader level, is dropped
+    /// first.
+    pub fn reinitialize(&mut self, config: &Config) -> Result<(), Error<E>> {
+        self.fader = None;
+        #[cfg(feature = "effects")]
+        {
+            self.fade_last_ms = None;
+        }
+        self.apply(config)?;
+        self.write(Register::User2, RESET_SENTINEL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+    use crate::SoftStart;
+
+    fn config() -> Config {
+        Config {
+            bank: Bank::B1,
+            banks: [
+                Color::default(),
+                Color {
+                    r: 0x100,
+                    g: 0x080,
+                    b: 0x040,
+                },
+                Color::default(),
+            ],
+            fader: 0x40,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn reinitialize_after_device_reset() {
+        let (mut dev, model) = Model::driver();
+        let config = config();
+        dev.reinitialize(&config).unwrap();
+        assert!(!dev.detect_device_reset().unwrap());
+
+        model.reset();
+        assert!(dev.detect_device_reset().unwrap());
+
+        dev.reinitialize(&config).unwrap();
+        assert!(!dev.detect_device_reset().unwrap());
+        assert_eq!(dev.read_bank(Bank::B1).unwrap(), config.banks[1]);
+        assert_eq!(model.reg(Register::BankSel), 1);
+        assert_eq!(model.reg(Register::Fader), 0x40);
+        assert_eq!(model.reg(Register::User2), RESET_SENTINEL);
+    }
+
+    #[test]
+    fn reinitialize_drops_cached_fader() {
+        let (mut dev, model) = Model::driver();
+        dev.set_fader(0x10).unwrap();
+        model.reset();
+        model.set_reg(Register::Fader, 0x20);
+        dev.reinitialize(&Config {
+            fader: 0x30,
+            ..Config::default()
+        })
+        .unwrap();
+        assert!(!dev.fade_to_step(0x40).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x31);
+    }
+
+    #[test]
+    fn clear_keeps_sentinel_and_preset_id() {
+        let (mut dev, model) = Model::driver();
+        dev.reinitialize(&config()).unwrap();
+        dev.set_preset_id(7).unwrap();
+        dev.clear().unwrap();
+        assert!(!dev.detect_device_reset().unwrap());
+        assert_eq!(dev.get_preset_id().unwrap(), 7);
+        assert_eq!(model.reg(Register::Fader), 0);
+    }
+
+    #[test]
+    fn apply_is_one_block_write() {
+        let (mut dev, model) = Model::driver();
+        let config = config();
+        dev.apply(&config).unwrap();
+        let writes = model.writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0][0], Register::BankSel as u8);
+        assert_eq!(writes[0].len(), CONFIG_LEN + 1);
+        assert_eq!(dev.read_bank(Bank::B1).unwrap(), config.banks[1]);
+        assert_eq!(model.reg(Register::Fader), config.fader);
+        assert_eq!(model.reg(Register::FaultMask), config.fault_mask.0);
+    }
+
+    #[test]
+    fn defaults_match_default_config() {
+        let (dev, _) = Model::driver();
+        let encoded = dev.encode_config::<()>(&Config::default()).unwrap();
+        for (i, (&(register, default), &value)) in DEFAULTS.iter().zip(encoded.iter()).enumerate() {
+            assert_eq!(register as usize, i);
+            assert_eq!(default, value, "{:?}", register);
+        }
+    }
+
+    #[test]
+    fn first_non_default_after_reset_and_one_write() {
+        let (mut dev, model) = Model::driver();
+        assert_eq!(
+            dev.first_non_default().unwrap(),
+            Some((Register::Fader, 0x00))
+        );
+        dev.reset_to_defaults().unwrap();
+        assert_eq!(dev.first_non_default().unwrap(), None);
+        assert!(dev.is_default_config().unwrap());
+
+        dev.write(Register::Ctrl, 0x12).unwrap();
+        assert_eq!(
+            dev.first_non_default().unwrap(),
+            Some((Register::Ctrl, 0x12))
+        );
+        assert!(!dev.is_default_config().unwrap());
+
+        model.set_reg(Register::Ctrl, 0x00);
+        model.set_reg(Register::User1, 0x33);
+        model.set_reg(Register::Fault, 0x01);
+        assert!(dev.is_default_config().unwrap());
+    }
+
+    static PRESETS: [(u8, Config); 1] = [(
+        3,
+        Config {
+            bank: Bank::B2,
+            banks: [
+                Color::new(0, 0, 0),
+                Color::new(0, 0, 0),
+                Color::new(0x10, 0x20, 0x30),
+            ],
+            fader: 0x80,
+            ctrl: Ctrl(0),
+            ilimit: Ilimit(0x11),
+            fault_mask: FaultMask(0),
+        },
+    )];
+
+    #[test]
+    fn apply_preset_id_applies_known_presets_only() {
+        let (mut dev, model) = Model::driver();
+        dev.set_presets(&PRESETS);
+        dev.apply_preset_id(3).unwrap();
+        assert_eq!(model.reg(Register::BankSel), Bank::B2.sel());
+        assert_eq!(dev.read_bank(Bank::B2).unwrap(), PRESETS[0].1.banks[2]);
+        assert_eq!(model.reg(Register::Fader), 0x80);
+        assert_eq!(dev.get_preset_id().unwrap(), 3);
+
+        model.clear_ops();
+        assert!(matches!(
+            dev.apply_preset_id(4),
+            Err(Error::UnknownPreset(4))
+        ));
+        assert!(model.ops().is_empty());
+        assert_eq!(model.reg(Register::User1), 3);
+    }
+
+    /// Delay recording the total wait
+    struct Waited(u32);
+
+    impl DelayMs<u16> for Waited {
+        fn delay_ms(&mut self, ms: u16) {
+            self.0 += ms as u32;
+        }
+    }
+
+    #[test]
+    fn power_on_blocking_waits_out_the_soft_start() {
+        for softstart in [
+            SoftStart::None,
+            SoftStart::MS500,
+            SoftStart::MS1000,
+            SoftStart::MS2000,
+        ] {
+            let (mut dev, model) = Model::driver();
+            let mut config = config();
+            config.ctrl.set_softstart(softstart);
+            let mut delay = Waited(0);
+            dev.power_on_blocking(&config, &mut delay).unwrap();
+            assert_eq!(delay.0, softstart.as_millis() as u32);
+            assert_eq!(model.reg(Register::Ctrl), config.ctrl.0);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Brightness control independent of how it is implemented
+
+use hal::blocking::i2c;
+
+use crate::{Bank, Color, Error, LM3549};
+
+/// Something with an overall brightness of `0..=255`
+///
+/// Lets application code switch between dimming strategies without changing call sites.
+pub trait Dimmable {
+    /// Error of a brightness change
+    type Error;
+
+    /// Set the brightness
+    fn set_brightness(&mut self, level: u8) -> Result<(), Self::Error>;
+
+    /// Last brightness set
+    fn brightness(&self) -> u8;
+}
+
+/// Dims through the master fader, leaving bank currents alone
+///
+/// Only has an effect when Ctrl.mfe or Ctrl.pwm is set.
+pub struct FaderDimmer<'a, I2C> {
+    dev: &'a mut LM3549<I2C>,
+    level: u8,
+}
+
+/// Dims by scaling the currents of one bank
+///
+/// Currents are `base * level / 255`, so the color is kept at any brightness.
+pub struct BankScaler<'a, I2C> {
+    dev: &'a mut LM3549<I2C>,
+    bank: Bank,
+    base: Color,
+    level: u8,
+}
+
+/// Dimming strategy chosen at runtime
+pub enum DimStrategy<'a, I2C> {
+    /// Master fader
+    Fader(FaderDimmer<'a, I2C>),
+    /// Bank current scaling
+    Bank(BankScaler<'a, I2C>),
+}
+
+impl<I2C> LM3549<I2C> {
+    /// Dim through the master fader, starting from full brightness
+    pub fn fader_dimmer(&mut self) -> FaderDimmer<'_, I2C> {
+        FaderDimmer {
+            dev: self,
+            level: 255,
+        }
+    }
+
+    /// Dim `bank` by scaling `base`, its currents at full brightness
+    pub fn bank_scaler(&mut self, bank: Bank, base: Color) -> BankScaler<'_, I2C> {
+        BankScaler {
+            dev: self,
+            bank,
+            base,
+            level: 255,
+        }
+    }
+}
+
+impl<'a, I2C, E> Dimmable for FaderDimmer<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    type Error = Error<E>;
+
+    fn set_brightness(&mut self, level: u8) -> Result<(), Error<E>> {
+        self.dev.set_fader(level)?;
+        self.level = level;
+        Ok(())
+    }
+
+    fn brightness(&self) -> u8 {
+        self.level
+    }
+}
+
+impl<'a, I2C, E> Dimmable for BankScaler<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    type Error = Error<E>;
+
+    fn set_brightness(&mut self, level: u8) -> Result<(), Error<E>> {
+        let c = Color::default().lerp(self.base, level as u32, 255);
+        self.dev.write_bank_color(self.bank, c)?;
+        self.level = level;
+        Ok(())
+    }
+
+    fn brightness(&self) -> u8 {
+        self.level
+    }
+}
+
+impl<'a, I2C, E> Dimmable for DimStrategy<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    type Error = Error<E>;
+
+    fn set_brightness(&mut self, level: u8) -> Result<(), Error<E>> {
+        match self {
+            DimStrategy::Fader(d) => d.set_brightness(level),
+            DimStrategy::Bank(d) => d.set_brightness(level),
+        }
+    }
+
+    fn brightness(&self) -> u8 {
+        match self {
+            DimStrategy::Fader(d) => d.brightness(),
+            DimStrategy::Bank(d) => d.brightness(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+    use crate::Register;
+
+    const BASE: Color = Color::new(0x200, 0x100, 0x3FC);
+
+    /// Application code written once against the trait
+    fn dim<D: Dimmable>(d: &mut D) -> Result<(), D::Error> {
+        d.set_brightness(128)?;
+        assert_eq!(d.brightness(), 128);
+        Ok(())
+    }
+
+    #[test]
+    fn dim_through_the_fader() {
+        let (mut dev, model) = Model::driver();
+        let mut dimmer = dev.fader_dimmer();
+        assert_eq!(dimmer.brightness(), 255);
+        dim(&mut dimmer).unwrap();
+        assert_eq!(model.reg(Register::Fader), 128);
+        assert_eq!(dev.read_bank(Bank::B0).unwrap(), Color::default());
+    }
+
+    #[test]
+    fn dim_by_scaling_a_bank() {
+        let (mut dev, model) = Model::driver();
+        let mut scaler = dev.bank_scaler(Bank::B1, BASE);
+        assert_eq!(scaler.brightness(), 255);
+        dim(&mut scaler).unwrap();
+        assert_eq!(
+            dev.read_bank(Bank::B1).unwrap(),
+            Color::new(0x101, 0x80, 0x200)
+        );
+        assert_eq!(model.reg(Register::Fader), 0);
+    }
+
+    #[test]
+    fn dim_through_either_strategy() {
+        let (mut dev, model) = Model::driver();
+        dim(&mut DimStrategy::Fader(dev.fader_dimmer())).unwrap();
+        dim(&mut DimStrategy::Bank(dev.bank_scaler(Bank::B1, BASE))).unwrap();
+        assert_eq!(model.reg(Register::Fader), 128);
+        assert_eq!(
+            dev.read_bank(Bank::B1).unwrap(),
+            Color::new(0x101, 0x80, 0x200)
+        );
+    }
+}
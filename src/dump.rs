@@ -0,0 +1,240 @@
+use core::fmt;
+use core::ops::Index;
+
+use crate::{
+    code_to_ma, decode_code, Bank, Color, Ctrl, EepromCtrl, Fault, FaultMask, Ilimit, Register,
+    EEPROM_CTRL_ADDR,
+};
+
+/// Number of register addresses covered by a [`RegisterDump`], 0x00 to 0x1A
+pub const DUMP_LEN: usize = 0x1B;
+
+/// Raw contents of the register file from BankSel to User2
+///
+/// The reserved address 0x18 is not read and reads as zero. `Display` prints one register
+/// per line with its decoded fields, suitable for bug reports.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegisterDump(pub [u8; DUMP_LEN]);
+
+impl RegisterDump {
+    /// Assemble a dump from a read of BankSel to Fault and a read of User1 to User2
+    pub fn from_reads(config: [u8; 0x18], user: [u8; 2]) -> Self {
+        let mut dump = RegisterDump::default();
+        dump.0[..0x18].copy_from_slice(&config);
+        dump.0[0x19..].copy_from_slice(&user);
+        dump
+    }
+
+    /// Every register in the dump with its value, in address order
+    pub fn iter(&self) -> impl Iterator<Item = (Register, u8)> + '_ {
+        Register::iter().filter_map(move |r| Some((r, self.get(r)?)))
+    }
+
+    /// Value of a register, `None` for registers outside the dump
+    pub fn get(&self, register: Register) -> Option<u8> {
+        self.0.get(register as usize).copied()
+    }
+
+    /// Current code of the LSB/MSB pair ending at the MSB register `msb`
+    fn code(&self, msb: Register) -> u16 {
+        let a = msb as usize;
+        decode_code(&self.0[a - 1..=a])
+    }
+}
+
+/// Every register decoded into its type, see [`LM3549::snapshot`](crate::LM3549::snapshot)
+///
+/// Indexing by [`Register`] gives the raw bytes as read, for code treating the register map
+/// uniformly. Changes to the fields are not reflected in them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Registers {
+    /// Bank select code
+    pub bank_sel: u8,
+    /// Currents of banks B0, B1 and B2
+    pub banks: [Color; 3],
+    /// Master fader level
+    pub fader: u8,
+    /// Control register
+    pub ctrl: Ctrl,
+    /// Current limit register
+    pub ilimit: Ilimit,
+    /// Fault mask register
+    pub fault_mask: FaultMask,
+    /// Fault register
+    pub fault: Fault,
+    /// User register 1
+    pub user1: u8,
+    /// User register 2
+    pub user2: u8,
+    /// EEPROM control register
+    pub eeprom_ctrl: EepromCtrl,
+    raw: [u8; EEPROM_CTRL_ADDR as usize + 1],
+}
+
+impl Registers {
+    /// Decode a dump and the EEPROM control register, which is outside the dump
+    pub fn new(dump: &RegisterDump, eeprom_ctrl: EepromCtrl) -> Self {
+        let reg = |r: Register| dump.0[r as usize];
+        let bank = |b: Bank| {
+            let mut buf = [0u8; 6];
+            buf.copy_from_slice(&dump.0[b as usize..b as usize + 6]);
+            Color::from_le_bytes(buf)
+        };
+        let mut raw = [0u8; EEPROM_CTRL_ADDR as usize + 1];
+        raw[..DUMP_LEN].copy_from_slice(&dump.0);
+        raw[EEPROM_CTRL_ADDR as usize] = eeprom_ctrl.0;
+        Registers {
+            bank_sel: reg(Register::BankSel),
+            banks: [bank(Bank::B0), bank(Bank::B1), bank(Bank::B2)],
+            fader: reg(Register::Fader),
+            ctrl: Ctrl(reg(Register::Ctrl)),
+            ilimit: Ilimit(reg(Register::Ilimit)),
+            fault_mask: FaultMask(reg(Register::FaultMask)),
+            fault: Fault(reg(Register::Fault)),
+            user1: reg(Register::User1),
+            user2: reg(Register::User2),
+            eeprom_ctrl,
+            raw,
+        }
+    }
+}
+
+impl Index<Register> for Registers {
+    type Output = u8;
+
+    /// Raw value of a register as read
+    fn index(&self, register: Register) -> &u8 {
+        &self.raw[register as usize]
+    }
+}
+
+/// Whether a register is the MSB of a bank current pair
+fn is_current_msb(r: Register) -> bool {
+    matches!(r as u8, 0x02..=0x12) && r as u8 & 0x01 == 0
+}
+
+impl fmt::Display for RegisterDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (r, value) in self.iter() {
+            write!(f, "{} {:#04x} = {:#04x}", r, r as u8, value)?;
+            match r {
+                Register::Ctrl => write!(f, "  {:?}", Ctrl(value))?,
+                Register::Ilimit => write!(f, "  {}", Ilimit(value))?,
+                Register::FaultMask => write!(f, "  {:?}", FaultMask(value))?,
+                Register::Fault => write!(f, "  {:?}", Fault(value))?,
+                r if is_current_msb(r) => {
+                    let code = self.code(r);
+                    write!(f, "  code={} ({} mA)", code, code_to_ma(code))?
+                }
+                _ => {}
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for RegisterDump {
+    fn format(&self, f: defmt::Formatter) {
+        for (r, value) in self.iter() {
+            if is_current_msb(r) {
+                let code = self.code(r);
+                defmt::write!(
+                    f,
+                    "{} {=u8:#04x} = {=u8:#04x}  code={=u16} ({=u16} mA)\n",
+                    r,
+                    r as u8,
+                    value,
+                    code,
+                    code_to_ma(code)
+                );
+            } else {
+                defmt::write!(f, "{} {=u8:#04x} = {=u8:#04x}\n", r, r as u8, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+
+    #[test]
+    fn index_matches_typed_fields() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::BankSel, 0x02);
+        model.set_reg(Register::Ig1Lsb, 0x34);
+        model.set_reg(Register::Ig1Msb, 0x01);
+        model.set_reg(Register::Fader, 0x80);
+        model.set_reg(Register::Ctrl, 0x12);
+        model.set_reg(Register::Ilimit, 0x23);
+        model.set_reg(Register::User2, 0xA5);
+        model.set_reg(Register::EepromCtrl, 0x04);
+        let regs = dev.snapshot().unwrap();
+        assert_eq!(regs[Register::BankSel], regs.bank_sel);
+        assert_eq!(regs[Register::Ig1Lsb], 0x34);
+        assert_eq!(regs[Register::Ig1Msb], 0x01);
+        assert_eq!(regs.banks[1].g, 0x134);
+        assert_eq!(regs[Register::Fader], regs.fader);
+        assert_eq!(regs[Register::Ctrl], regs.ctrl.0);
+        assert_eq!(regs[Register::Ilimit], regs.ilimit.0);
+        assert_eq!(regs[Register::User2], regs.user2);
+        assert_eq!(regs[Register::EepromCtrl], regs.eeprom_ctrl.0);
+        for r in Register::iter() {
+            assert_eq!(regs[r], model.reg(r));
+        }
+    }
+
+    #[cfg(feature = "names")]
+    const SNAPSHOT: &str = "\
+BankSel 0x00 = 0x01
+Ir0Lsb 0x01 = 0xff
+Ir0Msb 0x02 = 0x03  code=1023 (700 mA)
+Ig0Lsb 0x03 = 0x00
+Ig0Msb 0x04 = 0x00  code=0 (0 mA)
+Ib0Lsb 0x05 = 0x00
+Ib0Msb 0x06 = 0x00  code=0 (0 mA)
+Ir1Lsb 0x07 = 0x00
+Ir1Msb 0x08 = 0x00  code=0 (0 mA)
+Ig1Lsb 0x09 = 0x34
+Ig1Msb 0x0a = 0x01  code=308 (211 mA)
+Ib1Lsb 0x0b = 0x00
+Ib1Msb 0x0c = 0x00  code=0 (0 mA)
+Ir2Lsb 0x0d = 0x00
+Ir2Msb 0x0e = 0x00  code=0 (0 mA)
+Ig2Lsb 0x0f = 0x00
+Ig2Msb 0x10 = 0x00  code=0 (0 mA)
+Ib2Lsb 0x11 = 0x00
+Ib2Msb 0x12 = 0x00  code=0 (0 mA)
+Fader 0x13 = 0x80
+Ctrl 0x14 = 0x12  Ctrl { softstart: MS500, timeout: MS125, mfe: true, pwm: false }
+Ilimit 0x15 = 0x23  Ilimit(pos=1500mA, neg=2200mA)
+FaultMask 0x16 = 0x0f  FaultMask { short: false, open: true, uvlo: true, tsd: true, ocp: true }
+Fault 0x17 = 0x04  Fault { short: None, open: None, uvlo: true, tsd: false, ocp: false }
+User1 0x19 = 0x00
+User2 0x1a = 0xa5
+";
+
+    #[test]
+    #[cfg(feature = "names")]
+    fn display_matches_snapshot() {
+        extern crate std;
+        use std::string::ToString;
+
+        let mut dump = RegisterDump::default();
+        dump.0[Register::BankSel as usize] = 0x01;
+        dump.0[Register::Ir0Lsb as usize] = 0xFF;
+        dump.0[Register::Ir0Msb as usize] = 0x03;
+        dump.0[Register::Ig1Lsb as usize] = 0x34;
+        dump.0[Register::Ig1Msb as usize] = 0x01;
+        dump.0[Register::Fader as usize] = 0x80;
+        dump.0[Register::Ctrl as usize] = 0x12;
+        dump.0[Register::Ilimit as usize] = 0x23;
+        dump.0[Register::FaultMask as usize] = 0x0F;
+        dump.0[Register::Fault as usize] = 0x04;
+        dump.0[Register::User2 as usize] = 0xA5;
+        assert_eq!(dump.to_string(), SNAPSHOT);
+    }
+}
@@ -0,0 +1,387 @@
+//! Lighting effects built on the master fader
+
+use hal::blocking::delay::DelayMs;
+use hal::blocking::i2c;
+
+use crate::{Error, LM3549};
+
+/// Monotonic millisecond time source for non-blocking effects
+pub trait Clock {
+    /// Milliseconds since an arbitrary epoch, wrapping on overflow
+    fn now_ms(&self) -> u32;
+}
+
+/// A single dimmable output, so effects can be written once for any channel
+pub trait LedChannel {
+    /// Error of a level write
+    type Error;
+
+    /// Set the output level, `0..=max_level()`
+    fn set_level(&mut self, code: u16) -> Result<(), Self::Error>;
+
+    /// Highest accepted level
+    fn max_level(&self) -> u16;
+}
+
+/// Ramp `led` linearly from `from` to `to` in `steps` writes, `step_ms` apart
+///
+/// Levels are clamped to [`LedChannel::max_level`]. The last write is always `to`, with
+/// `steps == 0` it is the only one.
+pub fn ramp<L, D>(
+    led: &mut L,
+    from: u16,
+    to: u16,
+    steps: u16,
+    step_ms: u16,
+    delay: &mut D,
+) -> Result<(), L::Error>
+where
+    L: LedChannel,
+    D: DelayMs<u16>,
+{
+    let max = led.max_level();
+    let (from, to) = (from.min(max) as i32, to.min(max) as i32);
+    for i in 1..steps {
+        let level = from + (to - from) * i as i32 / steps as i32;
+        led.set_level(level as u16)?;
+        delay.delay_ms(step_ms);
+    }
+    led.set_level(to as u16)
+}
+
+/// The master fader as a [`LedChannel`] with levels `0..=255`
+pub struct FaderChannel<'a, I2C> {
+    dev: &'a mut LM3549<I2C>,
+}
+
+impl<I2C> LM3549<I2C> {
+    /// Borrow the master fader as a [`LedChannel`]
+    pub fn fader_channel(&mut self) -> FaderChannel<'_, I2C> {
+        FaderChannel { dev: self }
+    }
+}
+
+impl<'a, I2C, E> LedChannel for FaderChannel<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    type Error = Error<E>;
+
+    fn set_level(&mut self, code: u16) -> Result<(), Error<E>> {
+        self.dev.set_fader(code.min(255) as u8)
+    }
+
+    fn max_level(&self) -> u16 {
+        255
+    }
+}
+
+/// Fades the master fader toward a target that may change at any time, e.g. a slider
+///
+/// Every [`FadeController::update`] moves the fader at most `step` levels toward the latest
+/// target and lands exactly on it, so a moving target never makes the fader overshoot or
+/// oscillate.
+#[derive(Copy, Clone, Debug)]
+pub struct FadeController {
+    target: u8,
+    step: u8,
+}
+
+impl FadeController {
+    /// Fade toward `target`, moving up to `step` levels per update
+    pub fn new(target: u8, step: u8) -> Self {
+        FadeController {
+            target,
+            step: step.max(1),
+        }
+    }
+
+    /// Change the target, taking effect from the next update
+    pub fn set_target(&mut self, target: u8) {
+        self.target = target;
+    }
+
+    /// Current target
+    pub fn target(&self) -> u8 {
+        self.target
+    }
+
+    /// Move the fader one step toward the target, `true` once it is there
+    ///
+    /// At most one transaction, none once the target is reached.
+    pub fn update<I2C, E>(&mut self, dev: &mut LM3549<I2C>) -> Result<bool, Error<E>>
+    where
+        I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+    {
+        let level = dev.fader_level()?;
+        if level == self.target {
+            return Ok(true);
+        }
+        let next = if level < self.target {
+            level.saturating_add(self.step).min(self.target)
+        } else {
+            level.saturating_sub(self.step).max(self.target)
+        };
+        dev.set_fader(next)?;
+        Ok(next == self.target)
+    }
+}
+
+/// Number of fader writes used for one breathing cycle
+pub const BREATHE_STEPS: u16 = 64;
+
+/// Rising half of a raised-cosine wave, scaled to 0..=255
+const BREATHE_TABLE: [u8; 33] = [
+    0, 1, 2, 5, 10, 15, 21, 29, 37, 47, 57, 67, 79, 90, 103, 115, 127, 140, 152, 165, 176, 188,
+    198, 208, 218, 226, 234, 240, 245, 250, 253, 254, 255,
+];
+
+/// Successive fader levels of a breathing effect
+///
+/// Each cycle starts at `min`, rises to `max` and falls back in [`BREATHE_STEPS`] steps.
+/// After the last cycle a final `max` level is yielded.
+#[derive(Copy, Clone, Debug)]
+pub struct Breathe {
+    min: u8,
+    max: u8,
+    step: u32,
+    total: u32,
+}
+
+impl Breathe {
+    /// Create a breathing sequence of `cycles` cycles, `None` if `min > max`
+    pub fn new(min: u8, max: u8, cycles: u16) -> Option<Self> {
+        if min > max {
+            return None;
+        }
+        Some(Breathe {
+            min,
+            max,
+            step: 0,
+            total: cycles as u32 * BREATHE_STEPS as u32,
+        })
+    }
+
+    /// Fader level at `step` within a cycle
+    fn level(&self, step: u16) -> u8 {
+        let half = BREATHE_STEPS / 2;
+        let idx = if step <= half {
+            step
+        } else {
+            BREATHE_STEPS - step
+        };
+        let span = (self.max - self.min) as u16;
+        self.min + (span * BREATHE_TABLE[idx as usize] as u16 / 255) as u8
+    }
+}
+
+impl Iterator for Breathe {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.step > self.total {
+            return None;
+        }
+        let level = if self.step == self.total {
+            self.max
+        } else {
+            self.level((self.step % BREATHE_STEPS as u32) as u16)
+        };
+        self.step += 1;
+        Some(level)
+    }
+}
+
+/// Candle flicker around a base fader level
+///
+/// Levels stay within `base ± depth`, clamped to the fader range. The output only depends
+/// on the random source, so a seeded generator gives a repeatable flicker.
+#[cfg(feature = "rand_core")]
+#[derive(Copy, Clone, Debug)]
+pub struct Flicker {
+    base: u8,
+    depth: u8,
+    level: u8,
+}
+
+#[cfg(feature = "rand_core")]
+impl Flicker {
+    /// Create a flicker around `base` with excursions of at most `depth`
+    pub fn new(base: u8, depth: u8) -> Self {
+        Flicker {
+            base,
+            depth,
+            level: base,
+        }
+    }
+
+    /// Next fader level, smoothed against the previous one
+    pub fn next_level<R: rand_core::RngCore>(&mut self, rng: &mut R) -> u8 {
+        let span = 2 * self.depth as u32 + 1;
+        let offset = (rng.next_u32() % span) as i16 - self.depth as i16;
+        let target = (self.base as i16 + offset).clamp(0, 255);
+        self.level = ((self.level as i16 + target) / 2) as u8;
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_mock::delay::MockNoop;
+
+    use crate::testing::Model;
+    use crate::Register;
+
+    /// Channel recording every level written
+    struct Levels {
+        levels: [u16; 8],
+        len: usize,
+    }
+
+    impl Levels {
+        fn new() -> Self {
+            Levels {
+                levels: [0; 8],
+                len: 0,
+            }
+        }
+
+        fn written(&self) -> &[u16] {
+            &self.levels[..self.len]
+        }
+    }
+
+    impl LedChannel for Levels {
+        type Error = ();
+
+        fn set_level(&mut self, code: u16) -> Result<(), ()> {
+            self.levels[self.len] = code;
+            self.len += 1;
+            Ok(())
+        }
+
+        fn max_level(&self) -> u16 {
+            1000
+        }
+    }
+
+    #[test]
+    fn ramp_writes_steps_up_to_the_endpoint() {
+        let mut led = Levels::new();
+        ramp(&mut led, 100, 500, 4, 10, &mut MockNoop::new()).unwrap();
+        assert_eq!(led.written(), [200, 300, 400, 500]);
+
+        let mut led = Levels::new();
+        ramp(&mut led, 500, 0, 3, 10, &mut MockNoop::new()).unwrap();
+        assert_eq!(led.written(), [334, 167, 0]);
+    }
+
+    #[test]
+    fn ramp_clamps_and_handles_zero_steps() {
+        let mut led = Levels::new();
+        ramp(&mut led, 0, 2000, 2, 10, &mut MockNoop::new()).unwrap();
+        assert_eq!(led.written(), [500, 1000]);
+
+        let mut led = Levels::new();
+        ramp(&mut led, 0, 300, 0, 10, &mut MockNoop::new()).unwrap();
+        assert_eq!(led.written(), [300]);
+    }
+
+    #[test]
+    fn ramp_the_fader_channel() {
+        let (mut dev, model) = Model::driver();
+        ramp(&mut dev.fader_channel(), 0, 400, 5, 1, &mut MockNoop::new()).unwrap();
+        assert_eq!(model.reg(Register::Fader), 255);
+        let faders = model
+            .writes()
+            .into_iter()
+            .filter(|w| w[0] == Register::Fader as u8)
+            .map(|w| w[1]);
+        assert!(faders.eq([51, 102, 153, 204, 255]));
+    }
+
+    #[test]
+    fn fade_controller_retargets_from_the_current_level() {
+        let (mut dev, model) = Model::driver();
+        dev.set_fader(0x10).unwrap();
+        let mut fade = FadeController::new(0x40, 0x08);
+        assert!(!fade.update(&mut dev).unwrap());
+        assert!(!fade.update(&mut dev).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x20);
+
+        fade.set_target(0x1C);
+        assert_eq!(fade.target(), 0x1C);
+        assert!(fade.update(&mut dev).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x1C);
+
+        fade.set_target(0x00);
+        assert!(!fade.update(&mut dev).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x14);
+
+        model.clear_ops();
+        fade.set_target(0x14);
+        assert!(fade.update(&mut dev).unwrap());
+        assert!(model.ops().is_empty());
+    }
+
+    /// Seeded xorshift32, a repeatable random source
+    #[cfg(feature = "rand_core")]
+    struct XorShift(u32);
+
+    #[cfg(feature = "rand_core")]
+    impl rand_core::RngCore for XorShift {
+        fn next_u32(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            rand_core::impls::next_u64_via_u32(self)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            rand_core::impls::fill_bytes_via_next(self, dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rand_core")]
+    fn flicker_stays_around_its_base() {
+        let mut flicker = Flicker::new(100, 20);
+        let mut rng = XorShift(0x1234_5678);
+        let mut sum = 0u32;
+        for _ in 0..1000 {
+            let level = flicker.next_level(&mut rng);
+            assert!((80..=120).contains(&level), "{}", level);
+            sum += level as u32;
+        }
+        let mean = sum / 1000;
+        assert!((97..=103).contains(&mean), "{}", mean);
+
+        let (mut a, mut b) = (Flicker::new(100, 20), Flicker::new(100, 20));
+        let (mut rng_a, mut rng_b) = (XorShift(7), XorShift(7));
+        for _ in 0..16 {
+            assert_eq!(a.next_level(&mut rng_a), b.next_level(&mut rng_b));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rand_core")]
+    fn flicker_clamps_to_the_fader_range() {
+        let mut rng = XorShift(42);
+        let mut low = Flicker::new(5, 30);
+        let mut high = Flicker::new(250, 30);
+        for _ in 0..1000 {
+            assert!(low.next_level(&mut rng) <= 35);
+            assert!(high.next_level(&mut rng) >= 220);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Adapters for embedded-hal 1.0 I2C buses and PWM traits
+
+use core::fmt::Debug;
+
+use embedded_hal_1::i2c::I2c;
+use embedded_hal_1::pwm::{self, ErrorKind, ErrorType, SetDutyCycle};
+use hal::blocking::i2c;
+
+use crate::{Error, LM3549};
+
+/// Wraps an embedded-hal 1.0 I2C bus for use with the driver
+///
+/// Works with shared buses from `embedded-hal-bus` such as `RefCellDevice` or
+/// `CriticalSectionDevice`, as those implement [`I2c`] themselves.
+pub struct Eh1I2c<T>(pub T);
+
+impl<T: I2c> i2c::Write for Eh1I2c<T> {
+    type Error = T::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), T::Error> {
+        self.0.write(address, bytes)
+    }
+}
+
+impl<T: I2c> i2c::Read for Eh1I2c<T> {
+    type Error = T::Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), T::Error> {
+        self.0.read(address, buffer)
+    }
+}
+
+impl<T: I2c> i2c::WriteRead for Eh1I2c<T> {
+    type Error = T::Error;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), T::Error> {
+        self.0.write_read(address, bytes, buffer)
+    }
+}
+
+impl<T: I2c> LM3549<Eh1I2c<T>> {
+    /// Create new LM3549 instance with default address on an embedded-hal 1.0 bus
+    pub fn new_eh1(i2c: T) -> Self {
+        LM3549::new(Eh1I2c(i2c))
+    }
+}
+
+/// Bus error of a [`FaderPwm`] write
+#[derive(Debug)]
+pub struct FaderPwmError<E>(pub Error<E>);
+
+impl<E: Debug> pwm::Error for FaderPwmError<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// The master fader as an embedded-hal 1.0 [`SetDutyCycle`] with a maximum duty of 255
+///
+/// Created by [`LM3549::fader_pwm`], which makes sure the fader is enabled.
+pub struct FaderPwm<'a, I2C> {
+    dev: &'a mut LM3549<I2C>,
+}
+
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    /// Borrow the master fader as a [`SetDutyCycle`], setting Ctrl.mfe if it is clear
+    pub fn fader_pwm(&mut self) -> Result<FaderPwm<'_, I2C>, Error<E>> {
+        let mut ctrl = self.get_ctrl()?;
+        if !ctrl.mfe() {
+            ctrl.set_mfe(true);
+            self.set_ctrl(ctrl)?;
+        }
+        Ok(FaderPwm { dev: self })
+    }
+}
+
+impl<'a, I2C, E> ErrorType for FaderPwm<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+    E: Debug,
+{
+    type Error = FaderPwmError<E>;
+}
+
+impl<'a, I2C, E> SetDutyCycle for FaderPwm<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+    E: Debug,
+{
+    fn max_duty_cycle(&self) -> u16 {
+        255
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), FaderPwmError<E>> {
+        self.dev
+            .set_fader(duty.min(255) as u8)
+            .map_err(FaderPwmError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+    use crate::{Ctrl, Register};
+
+    #[test]
+    fn fader_pwm_maps_duty_cycle_to_the_fader() {
+        let (mut dev, model) = Model::driver();
+        let mut pwm = dev.fader_pwm().unwrap();
+        assert!(Ctrl(model.reg(Register::Ctrl)).mfe());
+        assert_eq!(pwm.max_duty_cycle(), 255);
+
+        pwm.set_duty_cycle_fully_on().unwrap();
+        assert_eq!(model.reg(Register::Fader), 255);
+        pwm.set_duty_cycle_fraction(1, 2).unwrap();
+        assert_eq!(model.reg(Register::Fader), 127);
+        pwm.set_duty_cycle_percent(20).unwrap();
+        assert_eq!(model.reg(Register::Fader), 51);
+        pwm.set_duty_cycle_fully_off().unwrap();
+        assert_eq!(model.reg(Register::Fader), 0);
+        pwm.set_duty_cycle(1000).unwrap();
+        assert_eq!(model.reg(Register::Fader), 255);
+
+        model.fail_in(0);
+        assert!(matches!(
+            pwm.set_duty_cycle(10),
+            Err(FaderPwmError(Error::I2c(..)))
+        ));
+    }
+
+    #[test]
+    fn fader_pwm_keeps_an_enabled_fader() {
+        let (mut dev, model) = Model::driver();
+        let mut ctrl = Ctrl(0);
+        ctrl.set_mfe(true);
+        model.set_reg(Register::Ctrl, ctrl.0);
+        dev.fader_pwm().unwrap();
+        assert_eq!(
+            model.writes().into_iter().filter(|w| w.len() > 1).count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! JSON export of device state for host-side logging
+//!
+//! Field names follow the `Debug` and `Display` output of each type. Every exported type
+//! also deserializes from its JSON, so logged state can be loaded back.
+
+use core::fmt;
+
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::string::String;
+
+use crate::{
+    Ctrl, Fault, FaultMask, Ilimit, NegLimit, OpenShort, PosLimit, Register, RegisterDump,
+    SoftStart, Timeout,
+};
+
+/// Convert to a JSON string
+pub trait ToJson {
+    /// JSON representation
+    fn to_json_string(&self) -> serde_json::Result<String>;
+}
+
+impl<T: Serialize> ToJson for T {
+    fn to_json_string(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+impl Serialize for Ctrl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Ctrl", 4)?;
+        s.serialize_field("softstart", &self.softstart())?;
+        s.serialize_field("timeout", &self.timeout())?;
+        s.serialize_field("mfe", &self.mfe())?;
+        s.serialize_field("pwm", &self.pwm())?;
+        s.end()
+    }
+}
+
+/// Limits in milliamps
+impl Serialize for Ilimit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Ilimit", 2)?;
+        s.serialize_field("pos", &self.pos_limit().milliamps())?;
+        s.serialize_field("neg", &self.neg_limit().milliamps())?;
+        s.end()
+    }
+}
+
+impl Serialize for FaultMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("FaultMask", 5)?;
+        s.serialize_field("short", &self.short())?;
+        s.serialize_field("open", &self.open())?;
+        s.serialize_field("uvlo", &self.uvlo())?;
+        s.serialize_field("tsd", &self.tsd())?;
+        s.serialize_field("ocp", &self.ocp())?;
+        s.end()
+    }
+}
+
+impl Serialize for Fault {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Fault", 5)?;
+        s.serialize_field("short", &self.short())?;
+        s.serialize_field("open", &self.open())?;
+        s.serialize_field("uvlo", &self.uvlo())?;
+        s.serialize_field("tsd", &self.tsd())?;
+        s.serialize_field("ocp", &self.ocp())?;
+        s.end()
+    }
+}
+
+/// Raw value of every register by register name
+impl Serialize for RegisterDump {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (r, value) in self.iter() {
+            map.serialize_entry(&r, &value)?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct CtrlFields {
+    softstart: SoftStart,
+    timeout: Timeout,
+    mfe: bool,
+    pwm: bool,
+}
+
+impl<'de> Deserialize<'de> for Ctrl {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let f = CtrlFields::deserialize(deserializer)?;
+        let mut ctrl = Ctrl(0);
+        ctrl.set_softstart(f.softstart);
+        ctrl.set_timeout(f.timeout);
+        ctrl.set_mfe(f.mfe);
+        ctrl.set_pwm(f.pwm);
+        Ok(ctrl)
+    }
+}
+
+#[derive(Deserialize)]
+struct IlimitFields {
+    pos: u16,
+    neg: u16,
+}
+
+/// Limits in milliamps, which must be exact steps of the register
+impl<'de> Deserialize<'de> for Ilimit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let f = IlimitFields::deserialize(deserializer)?;
+        let pos = PosLimit::from_milliamps(f.pos)
+            .ok_or_else(|| de::Error::custom("invalid positive current limit"))?;
+        let neg = NegLimit::from_milliamps(f.neg)
+            .ok_or_else(|| de::Error::custom("invalid negative current limit"))?;
+        let mut ilimit = Ilimit(0);
+        ilimit.set_pos_limit(pos);
+        ilimit.set_neg_limit(neg);
+        Ok(ilimit)
+    }
+}
+
+#[derive(Deserialize)]
+struct FaultMaskFields {
+    short: bool,
+    open: bool,
+    uvlo: bool,
+    tsd: bool,
+    ocp: bool,
+}
+
+impl<'de> Deserialize<'de> for FaultMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let f = FaultMaskFields::deserialize(deserializer)?;
+        let bits = [f.short, f.open, f.uvlo, f.tsd, f.ocp];
+        let mask = bits.iter().fold(0, |mask, &bit| mask << 1 | bit as u8);
+        Ok(FaultMask(mask))
+    }
+}
+
+#[derive(Deserialize)]
+struct FaultFields {
+    short: OpenShort,
+    open: OpenShort,
+    uvlo: bool,
+    tsd: bool,
+    ocp: bool,
+}
+
+impl<'de> Deserialize<'de> for Fault {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let f = FaultFields::deserialize(deserializer)?;
+        Ok(Fault(
+            (f.short as u8) << 5
+                | (f.open as u8) << 3
+                | (f.uvlo as u8) << 2
+                | (f.tsd as u8) << 1
+                | f.ocp as u8,
+        ))
+    }
+}
+
+struct DumpVisitor;
+
+impl<'de> Visitor<'de> for DumpVisitor {
+    type Value = RegisterDump;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of register names to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RegisterDump, A::Error> {
+        let mut dump = RegisterDump::default();
+        while let Some((r, value)) = map.next_entry::<Register, u8>()? {
+            let slot = dump
+                .0
+                .get_mut(r as usize)
+                .ok_or_else(|| de::Error::custom("register outside the dump"))?;
+            *slot = value;
+        }
+        Ok(dump)
+    }
+}
+
+/// Registers missing from the map read as zero
+impl<'de> Deserialize<'de> for RegisterDump {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(DumpVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bank, Color, Config};
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        serde_json::from_str(&value.to_json_string().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let mut ctrl = Ctrl::default();
+        ctrl.set_softstart(SoftStart::MS500);
+        ctrl.set_mfe(true);
+        let config = Config {
+            bank: Bank::B2,
+            banks: [
+                Color::new(1, 2, 3),
+                Color::new(0x3FF, 0, 0x200),
+                Color::default(),
+            ],
+            fader: 0x80,
+            ctrl,
+            ilimit: Ilimit::default(),
+            fault_mask: FaultMask(0x15),
+        };
+        assert_eq!(round_trip(&config), config);
+    }
+
+    #[test]
+    fn fault_round_trip() {
+        for bits in 0..0x80 {
+            let fault = Fault(bits);
+            assert_eq!(round_trip(&fault), fault);
+        }
+    }
+
+    #[test]
+    fn register_dump_round_trip() {
+        let mut dump = RegisterDump::default();
+        for (i, b) in dump.0.iter_mut().enumerate() {
+            *b = i as u8 * 3;
+        }
+        dump.0[0x18] = 0;
+        assert_eq!(round_trip(&dump), dump);
+    }
+
+    #[test]
+    fn field_names_match_debug() {
+        let json = Fault(0x04).to_json_string().unwrap();
+        assert_eq!(
+            json,
+            r#"{"short":"None","open":"None","uvlo":true,"tsd":false,"ocp":false}"#
+        );
+    }
+}
@@ -5,6 +5,25 @@
 //!
 //! Datasheet: [LM3549](https://www.ti.com/lit/ds/symlink/lm3549.pdf)
 //!
+//! ## Features
+//!
+//! The register accessors, bank and fader control and fault handling are always available.
+//! The following default features can be disabled with `default-features = false` for
+//! builds where every byte counts:
+//!
+//!  * `names`: register name strings, registers print as their address without it
+//!  * `color-math`: HSV and color temperature conversions
+//!  * `effects`: fader effects, animations and dimming adapters
+//!  * `eeprom`: EEPROM store and restore
+//!
+//! `scripts/check-features.sh` checks that a build without default features contains none
+//! of them.
+//!
+//! ## Errors
+//!
+//! Every fallible method returns [`Error`]. Bus errors come back as [`Error::I2c`] with the
+//! [`Operation`] that failed, the other variants are checks of the driver itself.
+//!
 //! ## License
 //!
 //! Licensed under either of
@@ -26,20 +45,343 @@
 
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 extern crate byteorder;
 extern crate embedded_hal as hal;
 
+#[cfg(feature = "eeprom")]
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use hal::blocking::delay::DelayMs;
 use hal::blocking::i2c;
+use hal::timer::CountDown;
 
+#[cfg(feature = "effects")]
+mod animation;
+#[cfg(feature = "embedded-hal-async")]
+mod asynch;
+mod bus;
+mod classify;
+mod color;
+mod config;
+#[cfg(feature = "effects")]
+mod dimming;
+mod dump;
+#[cfg(feature = "effects")]
+mod effects;
+#[cfg(feature = "eh1")]
+mod eh1;
+#[cfg(feature = "std")]
+mod json;
+mod macros;
+mod power;
 mod registers;
-use registers::*;
+#[cfg(feature = "critical-section")]
+mod shared;
+mod supervisor;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "effects")]
+pub use animation::*;
+pub use bus::{BusRecovery, OpKind, Operation};
+pub use classify::*;
+pub use color::*;
+pub use config::*;
+#[cfg(feature = "effects")]
+pub use dimming::*;
+pub use dump::*;
+#[cfg(feature = "effects")]
+pub use effects::*;
+#[cfg(feature = "eh1")]
+pub use eh1::*;
+#[cfg(feature = "std")]
+pub use json::*;
+pub use power::*;
+pub use registers::*;
+#[cfg(feature = "critical-section")]
+pub use shared::*;
+pub use supervisor::*;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use hal::blocking::i2c;
+
+    use crate::Bank;
+
+    /// No bank is listed twice
+    pub const fn distinct_banks(banks: &[Bank]) -> bool {
+        let mut i = 0;
+        while i < banks.len() {
+            let mut j = i + 1;
+            while j < banks.len() {
+                if banks[i] as u8 == banks[j] as u8 {
+                    return false;
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+        true
+    }
+}
 
 const LM3549_ADDR: u8 = 0x36;
 
+/// EEPROM status polls before giving up on an EEPROM operation
+#[cfg(feature = "eeprom")]
+const EEPROM_POLL_LIMIT: u16 = 1000;
+
+/// Number of configuration registers from BankSel to FaultMask, as stored in EEPROM
+const CONFIG_LEN: usize = 0x17;
+
+/// Wait after power-on before the first I2C access of [`LM3549::new_after_power_on`]
+///
+/// The datasheet gives no time for the I2C interface to become ready after VIN rises, this
+/// leaves a margin for the internal power-on reset.
+pub const POWER_ON_DELAY_MS: u8 = 10;
+
+/// Probes of [`LM3549::new_after_power_on`] before giving up, one delay apart
+pub const POWER_ON_PROBES: u8 = 3;
+
+/// Driver errors
+#[derive(Debug)]
+pub enum Error<E> {
+    /// I2C bus error during an operation
+    I2c(E, Operation),
+    /// I2C bus error after part of a multi-transaction write went through
+    ///
+    /// The registers written before the failing operation keep their new values.
+    PartialWrite {
+        /// Registers written before the failure
+        written: usize,
+        /// Bus error
+        source: E,
+        /// Failing operation
+        operation: Operation,
+    },
+    /// Lower bound of a range is above its upper bound
+    InvalidRange,
+    /// Bank select code is not 0, 1 or 2
+    InvalidBank,
+    /// Current code does not fit the 10-bit current registers
+    CodeOutOfRange {
+        /// Bank written
+        bank: Bank,
+        /// Channel with the code
+        channel: Channel,
+        /// Rejected code
+        code: u16,
+    },
+    /// Bank current exceeds the configured current budget
+    OverBudget {
+        /// Bank written
+        bank: Bank,
+        /// Rejected total current of the bank in milliamps
+        total_ma: u32,
+    },
+    /// Channel current exceeds the maximum of its installed [`LedSpec`]
+    OverLedLimit {
+        /// Bank written
+        bank: Bank,
+        /// Channel with the code
+        channel: Channel,
+        /// Rejected code
+        code: u16,
+    },
+    /// Device did not finish an operation in time
+    Timeout,
+    /// Block write runs past the end of the writable register span
+    BlockTooLong,
+    /// Register read back differs from the value expected
+    VerifyMismatch {
+        /// Register that differs
+        register: Register,
+        /// Expected value
+        expected: u8,
+        /// Value read back
+        actual: u8,
+    },
+    /// Neither Ctrl.mfe nor Ctrl.pwm is set, the fader has no effect
+    FaderDisabled,
+    /// FAULT pin could not be read
+    Pin,
+    /// No preset with the id in the preset table
+    UnknownPreset(u8),
+    /// Positive and negative current limits differ by more than [`Ilimit::MAX_RATIO`]
+    AsymmetricIlimit,
+    /// I2C address does not fit in 7 bits
+    InvalidAddress(u8),
+    /// Channel order does not list every channel once
+    InvalidChannelOrder,
+}
+
+/// Report a bus error after `written` registers of a multi-transaction write as
+/// [`Error::PartialWrite`], other errors and failures before any write unchanged
+fn partial<E>(written: usize) -> impl FnOnce(Error<E>) -> Error<E> {
+    move |e| match e {
+        Error::I2c(source, operation) if written > 0 => Error::PartialWrite {
+            written,
+            source,
+            operation,
+        },
+        e => e,
+    }
+}
+
+/// Describes the driver error only, the bus error of [`Error::I2c`] is its source
+impl<E> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::I2c(_, op) => write!(f, "I2C bus error on {}", op),
+            Error::PartialWrite {
+                written, operation, ..
+            } => write!(
+                f,
+                "I2C bus error on {} after {} registers written",
+                operation, written
+            ),
+            Error::InvalidRange => f.write_str("lower bound above upper bound"),
+            Error::InvalidBank => f.write_str("invalid bank select code"),
+            Error::CodeOutOfRange {
+                bank,
+                channel,
+                code,
+            } => write!(
+                f,
+                "current code {:#05x} out of range for channel {:?} in bank {:?}",
+                code, channel, bank
+            ),
+            Error::OverBudget { bank, total_ma } => {
+                write!(f, "bank {:?} current {} mA over budget", bank, total_ma)
+            }
+            Error::OverLedLimit {
+                bank,
+                channel,
+                code,
+            } => write!(
+                f,
+                "current code {:#05x} for channel {:?} in bank {:?} over LED limit",
+                code, channel, bank
+            ),
+            Error::Timeout => f.write_str("timed out"),
+            Error::BlockTooLong => f.write_str("block write past writable registers"),
+            Error::VerifyMismatch {
+                register,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "verify failed for {} ({:#04x}): wrote {:#04x}, read {:#04x}",
+                register, *register as u8, expected, actual
+            ),
+            Error::FaderDisabled => f.write_str("fader disabled"),
+            Error::Pin => f.write_str("FAULT pin error"),
+            Error::UnknownPreset(id) => write!(f, "unknown preset {}", id),
+            Error::AsymmetricIlimit => f.write_str("asymmetric current limits"),
+            Error::InvalidAddress(address) => write!(f, "invalid I2C address {:#04x}", address),
+            Error::InvalidChannelOrder => f.write_str("channel order repeats a channel"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::I2c(e, _) | Error::PartialWrite { source: e, .. } => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Error of [`LM3549::check_fault`]
+#[derive(Debug)]
+pub enum FaultError<E> {
+    /// Fault register reports an active fault
+    Device(Fault),
+    /// Fault register could not be read
+    Bus(Error<E>),
+}
+
+impl<E> From<Error<E>> for FaultError<E> {
+    fn from(e: Error<E>) -> Self {
+        FaultError::Bus(e)
+    }
+}
+
+/// Describes the fault only, the driver error of [`FaultError::Bus`] is its source
+impl<E> fmt::Display for FaultError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaultError::Device(fault) => write!(f, "device fault: {:?}", fault),
+            FaultError::Bus(_) => f.write_str("fault register read failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for FaultError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FaultError::Bus(e) => Some(e),
+            FaultError::Device(_) => None,
+        }
+    }
+}
+
+/// Under voltage lock-out transition reported to the [`LM3549::on_uvlo`] hook
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UvloEvent {
+    /// Fault register started reporting UVLO
+    Entered,
+    /// Fault register stopped reporting UVLO
+    Recovered,
+}
+
+/// How bank writes handle currents outside the allowed range
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RangePolicy {
+    /// Return an error without touching the bus
+    Reject,
+    /// Clamp codes to 10 bits and scale banks down to the current budget
+    Clamp,
+}
+
+impl Default for RangePolicy {
+    /// Reject out of range writes
+    fn default() -> Self {
+        RangePolicy::Reject
+    }
+}
+
 /// LM3549 High Power Sequential LED Driver
+///
+/// Most methods are a single I2C transaction and can run while holding a lock or critical
+/// section. Methods that take a delay or timer, and read-modify-write helpers, are multiple
+/// transactions; the long-running ones have `_step` counterparts that do one transaction
+/// per call.
 pub struct LM3549<I2C> {
     i2c: I2C,
     address: u8,
+    policy: RangePolicy,
+    budget_ma: Option<u16>,
+    led_specs: Option<[LedSpec; 3]>,
+    channel_order: [Channel; 3],
+    fader: Option<u8>,
+    #[cfg(feature = "effects")]
+    fade_interval_ms: u16,
+    #[cfg(feature = "effects")]
+    fade_last_ms: Option<u32>,
+    recovery: Option<BusRecovery>,
+    bus_failures: u8,
+    uvlo_hook: Option<fn(UvloEvent)>,
+    uvlo: bool,
+    presets: &'static [(u8, Config)],
+    golden: Option<Config>,
 }
 
 impl<I2C> LM3549<I2C> {
@@ -48,75 +390,2162 @@ impl<I2C> LM3549<I2C> {
         LM3549 {
             i2c,
             address: LM3549_ADDR,
+            policy: RangePolicy::default(),
+            budget_ma: None,
+            led_specs: None,
+            channel_order: Channel::all(),
+            fader: None,
+            #[cfg(feature = "effects")]
+            fade_interval_ms: 4,
+            #[cfg(feature = "effects")]
+            fade_last_ms: None,
+            recovery: None,
+            bus_failures: 0,
+            uvlo_hook: None,
+            uvlo: false,
+            presets: &[],
+            golden: None,
         }
     }
+
+    /// 7-bit I2C address the driver talks to
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Call `hook` when fault reads enter or leave under voltage lock-out
+    ///
+    /// Driven by [`LM3549::get_fault`] and [`LM3549::read_fault_into`], so the hook runs once
+    /// per transition however often the faults are polled.
+    pub fn on_uvlo(&mut self, hook: fn(UvloEvent)) {
+        self.uvlo_hook = Some(hook);
+    }
+
+    /// Remove the UVLO hook
+    pub fn clear_uvlo_hook(&mut self) {
+        self.uvlo_hook = None;
+    }
+
+    /// Report a UVLO transition in a fault read to the hook
+    fn track_uvlo(&mut self, fault: Fault) {
+        if let Some(hook) = self.uvlo_hook {
+            match (self.uvlo, fault.uvlo()) {
+                (false, true) => hook(UvloEvent::Entered),
+                (true, false) => hook(UvloEvent::Recovered),
+                _ => {}
+            }
+        }
+        self.uvlo = fault.uvlo();
+    }
+
+    /// Destroy the driver and return the bus
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Set how bank writes handle out of range currents
+    pub fn set_range_policy(&mut self, policy: RangePolicy) {
+        self.policy = policy;
+    }
+
+    /// Limit the summed red, green and blue current of every bank write
+    pub fn set_current_budget_ma(&mut self, total_ma: u16) {
+        self.budget_ma = Some(total_ma);
+    }
+
+    /// Remove the current budget
+    pub fn clear_current_budget(&mut self) {
+        self.budget_ma = None;
+    }
+
+    /// Set the time between fader steps of [`LM3549::fade_step`], 4 ms by default
+    #[cfg(feature = "effects")]
+    pub fn set_fade_interval(&mut self, interval_ms: u16) {
+        self.fade_interval_ms = interval_ms;
+    }
+
+    /// Install the LEDs connected to the red, green and blue channels
+    ///
+    /// Bank writes then respect each LED's maximum current and the power estimates use the
+    /// LED forward voltages.
+    pub fn set_led_specs(&mut self, specs: [LedSpec; 3]) {
+        self.led_specs = Some(specs);
+    }
+
+    /// Installed LED specs, or [`LedSpec::default`] for every channel
+    pub fn led_specs(&self) -> [LedSpec; 3] {
+        self.led_specs.unwrap_or_default()
+    }
+
+    /// Physical channel driving the logical `channel`
+    fn physical(&self, channel: Channel) -> Channel {
+        self.channel_order[channel as usize]
+    }
+
+    /// Map logical channel codes to the physical channels
+    fn to_physical(&self, c: Color) -> Color {
+        let mut p = Color::default();
+        for channel in Channel::all() {
+            p.set(self.physical(channel), c.get(channel));
+        }
+        p
+    }
+
+    /// Map physical channel codes to the logical channels
+    fn to_logical(&self, p: Color) -> Color {
+        let mut c = Color::default();
+        for channel in Channel::all() {
+            c.set(channel, p.get(self.physical(channel)));
+        }
+        c
+    }
+
+    /// Apply the range policy, LED limits and current budget to a bank write
+    fn limit<E>(&self, bank: Bank, c: Color) -> Result<Color, Error<E>> {
+        if self.policy == RangePolicy::Reject {
+            for channel in Channel::all() {
+                let code = c.get(channel);
+                if code > MAX_CODE {
+                    return Err(Error::CodeOutOfRange {
+                        bank,
+                        channel,
+                        code,
+                    });
+                }
+            }
+        }
+        let mut c = c.clamped();
+        if let Some(specs) = self.led_specs {
+            for (&channel, spec) in Channel::all().iter().zip(specs.iter()) {
+                let (code, max) = (c.get(channel), spec.max_code());
+                if code > max {
+                    match self.policy {
+                        RangePolicy::Reject => {
+                            return Err(Error::OverLedLimit {
+                                bank,
+                                channel,
+                                code,
+                            })
+                        }
+                        RangePolicy::Clamp => c.set(channel, max),
+                    }
+                }
+            }
+        }
+        let budget_ua = match self.budget_ma {
+            Some(ma) => ma as u32 * 1000,
+            None => return Ok(c),
+        };
+        let total_ua = c.total() * CURRENT_LSB_UA;
+        if total_ua <= budget_ua {
+            return Ok(c);
+        }
+        match self.policy {
+            RangePolicy::Reject => Err(Error::OverBudget {
+                bank,
+                total_ma: total_ua / 1000,
+            }),
+            RangePolicy::Clamp => {
+                let scale = |v: u16| (v as u32 * budget_ua / total_ua) as u16;
+                Ok(Color {
+                    r: scale(c.r),
+                    g: scale(c.g),
+                    b: scale(c.b),
+                })
+            }
+        }
+    }
+
+    /// Apply the range policy to all banks and encode their current registers, B0 first
+    fn limit_banks<E>(&self, banks: &[Color; 3]) -> Result<[u8; 18], Error<E>> {
+        let mut data = [0u8; 18];
+        for ((bank, c), buf) in Bank::iter().zip(banks.iter()).zip(data.chunks_mut(6)) {
+            let c = self.limit::<E>(bank, self.to_physical(*c))?;
+            buf.copy_from_slice(&c.to_le_bytes());
+        }
+        Ok(data)
+    }
 }
 
 impl<I2C, E> LM3549<I2C>
 where
     I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
 {
+    /// Create new LM3549 instance with default address once the device answers after power-on
+    ///
+    /// Waits [`POWER_ON_DELAY_MS`] before each of up to [`POWER_ON_PROBES`] reads of the
+    /// control register. Returns the bus with the last error if the device never answers.
+    pub fn new_after_power_on<D: DelayMs<u8>>(
+        i2c: I2C,
+        delay: &mut D,
+    ) -> Result<Self, (I2C, Error<E>)> {
+        let mut dev = LM3549::new(i2c);
+        let mut probes = 0;
+        loop {
+            delay.delay_ms(POWER_ON_DELAY_MS);
+            probes += 1;
+            match dev.get_ctrl() {
+                Ok(_) => return Ok(dev),
+                Err(e) if probes >= POWER_ON_PROBES => return Err((dev.release(), e)),
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Set the physical channels driving the logical red, green and blue channels
+    ///
+    /// For LEDs wired in another order, e.g. `[Red, Blue, Green]` with green and blue
+    /// swapped. Bank and channel writes and reads then use logical channels, while LED specs
+    /// and range errors refer to the physical channels. `order` must list every channel
+    /// once, otherwise [`Error::InvalidChannelOrder`]; the default is `[Red, Green, Blue]`.
+    pub fn set_channel_order(&mut self, order: [Channel; 3]) -> Result<(), Error<E>> {
+        if !Channel::all().iter().all(|ch| order.contains(ch)) {
+            return Err(Error::InvalidChannelOrder);
+        }
+        self.channel_order = order;
+        Ok(())
+    }
+
+    /// Talk to the device at another 7-bit I2C address, e.g. behind an address translator
+    pub fn set_address(&mut self, address: u8) -> Result<(), Error<E>> {
+        if address > 0x7F {
+            return Err(Error::InvalidAddress(address));
+        }
+        self.address = address;
+        Ok(())
+    }
+
     /// Read a register
-    pub fn read(&mut self, register: Register) -> Result<u8, E> {
+    pub fn read(&mut self, register: Register) -> Result<u8, Error<E>> {
         let mut buf: [u8; 1] = [0x00];
-        self.i2c.write(self.address, &[register as u8])?;
-        self.i2c.read(self.address, &mut buf)?;
+        self.bus_read(register as u8, &mut buf)?;
         Ok(buf[0])
     }
 
+    /// Read consecutive registers starting at `start`
+    pub fn read_registers(&mut self, start: Register, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.bus_read(start as u8, buf)
+    }
+
+    /// Read the register file from BankSel to User2
+    pub fn dump_registers(&mut self) -> Result<RegisterDump, Error<E>> {
+        let (mut config, mut user) = ([0u8; 0x18], [0u8; 2]);
+        self.read_registers(Register::BankSel, &mut config)?;
+        self.read_registers(Register::User1, &mut user)?;
+        Ok(RegisterDump::from_reads(config, user))
+    }
+
+    /// Read and decode every register, BankSel to User2 and EepromCtrl
+    ///
+    /// Reads the fault register, see [`LM3549::get_fault`].
+    pub fn snapshot(&mut self) -> Result<Registers, Error<E>> {
+        let dump = self.dump_registers()?;
+        let eeprom_ctrl = EepromCtrl(self.read(Register::EepromCtrl)?);
+        Ok(Registers::new(&dump, eeprom_ctrl))
+    }
+
     /// Get active faults
-    pub fn get_fault(&mut self) -> Result<Fault, E> {
-        let x = self.read(Register::Fault)?;
-        Ok(Fault(x))
+    pub fn get_fault(&mut self) -> Result<Fault, Error<E>> {
+        let fault = Fault(self.read(Register::Fault)?);
+        self.track_uvlo(fault);
+        Ok(fault)
+    }
+
+    /// Read the fault register, `Err` if it reports an active fault
+    pub fn check_fault(&mut self) -> Result<(), FaultError<E>> {
+        let fault = self.get_fault()?;
+        if fault.is_err() {
+            return Err(FaultError::Device(fault));
+        }
+        Ok(())
+    }
+
+    /// Read fault register into `out`, e.g. a `Fault` kept in a static by an interrupt handler
+    pub fn read_fault_into(&mut self, out: &mut Fault) -> Result<(), Error<E>> {
+        *out = self.get_fault()?;
+        Ok(())
+    }
+
+    /// Get fault mask register
+    pub fn get_fault_mask(&mut self) -> Result<FaultMask, Error<E>> {
+        let x = self.read(Register::FaultMask)?;
+        Ok(FaultMask(x))
+    }
+
+    /// Whether the FAULT output is asserted, for boards without the pin connected
+    pub fn fault_pin_asserted(&mut self) -> Result<bool, Error<E>> {
+        let mask = self.get_fault_mask()?;
+        Ok(self.get_fault()?.asserts_pin(mask))
+    }
+
+    /// Sample every fault with the FAULT output enabled
+    ///
+    /// Enables all fault mask bits, reads the fault register and restores the previous mask,
+    /// also when the fault read fails.
+    pub fn diagnostic_fault_cycle(&mut self) -> Result<Fault, Error<E>> {
+        let mask = self.get_fault_mask()?;
+        self.set_fault_mask(FaultMask::all())?;
+        let fault = self.get_fault();
+        self.set_fault_mask(mask)?;
+        fault
+    }
+
+    /// Write consecutive registers starting at `start`
+    ///
+    /// The block must stay within one writable span: BankSel to FaultMask, User1 to User2 or
+    /// EepromCtrl alone. A read-only `start` such as Fault is [`Error::BlockTooLong`] even
+    /// for empty `data`; otherwise empty `data` sends nothing.
+    pub fn write_block(&mut self, start: Register, data: &[u8]) -> Result<(), Error<E>> {
+        let end = match start as u8 {
+            0x00..=0x16 => 0x17,
+            0x19..=0x1A => 0x1B,
+            EEPROM_CTRL_ADDR => EEPROM_CTRL_ADDR as usize + 1,
+            _ => return Err(Error::BlockTooLong),
+        };
+        if start as usize + data.len() > end {
+            return Err(Error::BlockTooLong);
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+        let mut buf = [0u8; CONFIG_LEN + 1];
+        buf[0] = start as u8;
+        buf[1..=data.len()].copy_from_slice(data);
+        self.bus_write(&buf[..=data.len()])?;
+        let fader = Register::Fader as usize;
+        if (start as usize..start as usize + data.len()).contains(&fader) {
+            self.fader = Some(data[fader - start as usize]);
+        }
+        Ok(())
     }
 
     /// Write a register
-    pub fn write(&mut self, register: Register, value: u8) -> Result<(), E> {
+    pub fn write(&mut self, register: Register, value: u8) -> Result<(), Error<E>> {
         let buf = [register as u8, value];
-        self.i2c.write(self.address, &buf)
+        self.bus_write(&buf)?;
+        if register == Register::Fader {
+            self.fader = Some(value);
+        }
+        Ok(())
     }
 
-    /// Write a register
-    pub fn write_bank(&mut self, bank: Bank, r: u16, g: u16, b: u16) -> Result<(), E> {
-        let buf = [
-            bank as u8,
-            (r & 0xFF) as u8,
-            ((r >> 8) & 0x03) as u8,
-            (g & 0xFF) as u8,
-            ((g >> 8) & 0x03) as u8,
-            (b & 0xFF) as u8,
-            ((b >> 8) & 0x03) as u8,
-        ];
-        self.i2c.write(self.address, &buf)
+    /// Make sure all previous writes have reached the device
+    ///
+    /// Every write currently goes to the bus immediately, so this does nothing. Call it where
+    /// writes must have taken effect, it stays correct if writes are ever cached or queued.
+    pub fn flush(&mut self) -> Result<(), Error<E>> {
+        Ok(())
+    }
+
+    /// Write the current settings of a bank
+    #[deprecated(note = "use `write_bank_currents`, positional codes are easily swapped")]
+    pub fn write_bank(&mut self, bank: Bank, r: u16, g: u16, b: u16) -> Result<(), Error<E>> {
+        self.write_bank_currents(bank, BankCurrents { r, g, b })
+    }
+
+    /// Write the current settings of a bank
+    pub fn write_bank_currents(&mut self, bank: Bank, c: BankCurrents) -> Result<(), Error<E>> {
+        self.write_bank_color(bank, c)
+    }
+
+    /// Write a bank without applying the range policy or current budget
+    fn write_bank_raw(&mut self, bank: Bank, c: Color) -> Result<(), Error<E>> {
+        // Bank discriminants are register addresses, not bank select codes
+        debug_assert!(matches!(bank as u8, 0x01 | 0x07 | 0x0D));
+        let mut buf = [0u8; 7];
+        buf[0] = bank as u8;
+        buf[1..].copy_from_slice(&c.to_le_bytes());
+        self.bus_write(&buf)
+    }
+
+    /// Write a 10-bit code to a LSB/MSB register pair
+    fn write_u16_pair(&mut self, lsb_reg: Register, value: u16) -> Result<(), Error<E>> {
+        let mut buf = [lsb_reg as u8, 0, 0];
+        encode_code(&mut buf[1..], value);
+        self.bus_write(&buf)
+    }
+
+    /// Read a 10-bit code from a LSB/MSB register pair
+    fn read_u16_pair(&mut self, lsb_reg: Register) -> Result<u16, Error<E>> {
+        let mut buf = [0u8; 2];
+        self.read_registers(lsb_reg, &mut buf)?;
+        Ok(decode_code(&buf))
+    }
+
+    /// Write the current code of one channel in a bank
+    ///
+    /// The rest of the bank is read to apply the [`RangePolicy`]; if the policy has to
+    /// adjust other channels, the whole bank is written.
+    pub fn write_channel(
+        &mut self,
+        bank: Bank,
+        channel: Channel,
+        code: u16,
+    ) -> Result<(), Error<E>> {
+        let channel = self.physical(channel);
+        let mut c = self.read_bank_raw(bank)?;
+        c.set(channel, code);
+        let limited = self.limit(bank, c)?;
+        if limited == c {
+            let reg = bank_channel_reg(bank, channel, false);
+            self.write_u16_pair(reg, code)
+        } else {
+            self.write_bank_raw(bank, limited)
+        }
+    }
+
+    /// Read the current code of one channel in a bank
+    ///
+    /// Like [`LM3549::read_bank`] this never changes the selected bank.
+    pub fn read_channel(&mut self, bank: Bank, channel: Channel) -> Result<u16, Error<E>> {
+        let channel = self.physical(channel);
+        self.read_u16_pair(bank_channel_reg(bank, channel, false))
+    }
+
+    /// Read the current settings of a bank
+    ///
+    /// Every bank has its own registers, so they are read by address and the selected bank
+    /// is never changed; the outputs keep showing whatever bank is selected.
+    pub fn read_bank(&mut self, bank: Bank) -> Result<Color, Error<E>> {
+        let p = self.read_bank_raw(bank)?;
+        Ok(self.to_logical(p))
+    }
+
+    /// Read a bank by physical channel
+    fn read_bank_raw(&mut self, bank: Bank) -> Result<Color, Error<E>> {
+        let mut buf = [0u8; 6];
+        self.bus_read(bank as u8, &mut buf)?;
+        Ok(Color::from_le_bytes(buf))
+    }
+
+    /// Read the current settings of the selected bank, `None` for an invalid select code
+    ///
+    /// Only reads, for status displays that must not disturb the outputs.
+    pub fn selected_color(&mut self) -> Result<Option<Color>, Error<E>> {
+        match self.selected_bank()? {
+            Some(bank) => self.read_bank(bank).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Number of channels of a bank with a nonzero current, 0 to 3
+    pub fn active_channel_count(&mut self, bank: Bank) -> Result<u8, Error<E>> {
+        let c = self.read_bank(bank)?;
+        Ok(Channel::all().iter().filter(|&&ch| c.get(ch) != 0).count() as u8)
+    }
+
+    /// Read the current settings of all banks, B0 first
+    pub fn read_all_banks(&mut self) -> Result<[Color; 3], Error<E>> {
+        Ok([
+            self.read_bank(Bank::B0)?,
+            self.read_bank(Bank::B1)?,
+            self.read_bank(Bank::B2)?,
+        ])
+    }
+
+    /// Sum of the current codes of a bank
+    pub fn bank_load(&mut self, bank: Bank) -> Result<u32, Error<E>> {
+        Ok(self.read_bank(bank)?.total())
+    }
+
+    /// Estimate the converter input current in milliamps for a bank
+    ///
+    /// Assumes all three channels of the bank run at once, see
+    /// [`estimate_input_current_ma`].
+    pub fn estimate_input_current(
+        &mut self,
+        bank: Bank,
+        vin_mv: u16,
+        vled_mv: u16,
+        efficiency_percent: u8,
+    ) -> Result<u32, Error<E>> {
+        let iled_ma = self.read_bank(bank)?.total_ma();
+        Ok(estimate_input_current_ma(
+            vin_mv,
+            vled_mv,
+            iled_ma,
+            efficiency_percent,
+        ))
+    }
+
+    /// Estimate the converter input current in milliamps for a bank from the LED specs
+    ///
+    /// Falls back to [`LedSpec::default`] when no specs are installed.
+    pub fn estimate_bank_input_current(
+        &mut self,
+        bank: Bank,
+        vin_mv: u16,
+        efficiency_percent: u8,
+    ) -> Result<u32, Error<E>> {
+        let c = self.read_bank_raw(bank)?;
+        let specs = self.led_specs();
+        Ok(estimate_bank_input_current_ma(
+            vin_mv,
+            &c,
+            &specs,
+            efficiency_percent,
+        ))
+    }
+
+    /// Suggest a current limit for a bank from the LED specs, see [`suggest_ilimit`]
+    ///
+    /// Uses the highest forward voltage of the lit channels as output voltage and falls back
+    /// to [`LedSpec::default`] when no specs are installed.
+    pub fn suggest_bank_ilimit(
+        &mut self,
+        bank: Bank,
+        vin_mv: u16,
+        efficiency_percent: u8,
+    ) -> Result<Option<Ilimit>, Error<E>> {
+        let c = self.read_bank_raw(bank)?;
+        let specs = self.led_specs();
+        let vout_mv = Channel::all()
+            .iter()
+            .zip(specs.iter())
+            .filter(|&(&channel, _)| c.get(channel) > 0)
+            .map(|(_, spec)| spec.vf_mv)
+            .max()
+            .unwrap_or(0);
+        let led_ma = c.total_ma().min(u16::MAX as u32) as u16;
+        Ok(suggest_ilimit(vin_mv, vout_mv, led_ma, efficiency_percent))
+    }
+
+    /// Write a bank from a [`Color`] or anything convertible into one
+    ///
+    /// Codes above 10 bits and banks over the current budget are handled according to the
+    /// [`RangePolicy`].
+    pub fn write_bank_color(&mut self, bank: Bank, c: impl Into<Color>) -> Result<(), Error<E>> {
+        let c = self.limit(bank, self.to_physical(c.into()))?;
+        self.write_bank_raw(bank, c)
+    }
+
+    /// Write an 8-bit per channel pixel to a bank, expanding it to 10 bits
+    ///
+    /// Goes through the same range checks as [`LM3549::write_bank_color`].
+    pub fn write_bank_rgb8(&mut self, bank: Bank, rgb: [u8; 3]) -> Result<(), Error<E>> {
+        self.write_bank_color(bank, Color::from_rgb8(rgb[0], rgb[1], rgb[2]))
+    }
+
+    /// Write red, green, blue and white codes to a bank, emulating RGBW on the RGB outputs
+    ///
+    /// White is added to every channel, saturating at [`MAX_CODE`]. This is only an
+    /// approximation: the mix of the RGB LEDs is rarely a neutral white and saturated
+    /// channels lose part of the white.
+    pub fn set_rgbw(&mut self, bank: Bank, r: u16, g: u16, b: u16, w: u16) -> Result<(), Error<E>> {
+        let add = |c: u16| c.saturating_add(w).min(MAX_CODE);
+        self.write_bank_currents(
+            bank,
+            BankCurrents {
+                r: add(r),
+                g: add(g),
+                b: add(b),
+            },
+        )
+    }
+
+    /// Write an approximate white of color temperature `kelvin` to a bank
+    ///
+    /// See [`Color::from_kelvin`] for the approximation used.
+    #[cfg(feature = "color-math")]
+    pub fn set_color_temp(&mut self, bank: Bank, kelvin: u16, level: u8) -> Result<(), Error<E>> {
+        self.write_bank_color(bank, Color::from_kelvin(kelvin, level))
+    }
+
+    /// Write zero to every writable register except the user registers
+    ///
+    /// Leaves all bank currents, the fader and the control register at zero and the device
+    /// dark. This differs from the power-on defaults, e.g. [`Ilimit::default`], which are not
+    /// all zero. User1 and User2 keep the preset id and the [`RESET_SENTINEL`].
+    ///
+    /// Takes one transaction per bank and register; a bus error after the first is reported
+    /// as [`Error::PartialWrite`].
+    pub fn clear(&mut self) -> Result<(), Error<E>> {
+        let mut written = 0;
+        for bank in Bank::iter() {
+            self.write_bank_raw(bank, Color::default())
+                .map_err(partial(written))?;
+            written += 6;
+        }
+        for &register in [
+            Register::BankSel,
+            Register::Fader,
+            Register::Ctrl,
+            Register::Ilimit,
+            Register::FaultMask,
+        ]
+        .iter()
+        {
+            self.write(register, 0x00).map_err(partial(written))?;
+            written += 1;
+        }
+        Ok(())
+    }
+
+    /// Write the next color of a hue sweep to a bank
+    #[cfg(feature = "color-math")]
+    pub fn rainbow_step(&mut self, bank: Bank, sweep: &mut HueSweep) -> Result<(), Error<E>> {
+        match sweep.next() {
+            Some(c) => self.write_bank_color(bank, c),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the currents of all three banks, B0 first, in one transaction
+    ///
+    /// All banks are checked against the [`RangePolicy`] before anything is written.
+    pub fn configure_all_banks(&mut self, banks: [BankCurrents; 3]) -> Result<(), Error<E>> {
+        let data = self.limit_banks(&banks)?;
+        self.write_block(Register::Ir0Lsb, &data)
+    }
+
+    /// Write all three banks, the currently selected bank last
+    ///
+    /// Writing the displayed bank last keeps a half-updated bank off the outputs. All banks
+    /// are checked against the [`RangePolicy`] before anything is written; a bus error after
+    /// the first bank is reported as [`Error::PartialWrite`].
+    pub fn write_banks_safe(&mut self, colors: &[(u16, u16, u16); 3]) -> Result<(), Error<E>> {
+        let mut limited = [Color::default(); 3];
+        for ((bank, &c), l) in Bank::iter().zip(colors.iter()).zip(limited.iter_mut()) {
+            *l = self.limit(bank, self.to_physical(c.into()))?;
+        }
+        let selected = self.selected_bank()?;
+        let mut written = 0;
+        let others = Bank::iter().filter(|&bank| Some(bank) != selected);
+        for bank in others.chain(selected) {
+            self.write_bank_raw(bank, limited[bank.sel() as usize])
+                .map_err(partial(written))?;
+            written += 6;
+        }
+        Ok(())
     }
 
     /// Select driver current settings bank
-    pub fn select_bank(&mut self, bank: Bank) -> Result<(), E> {
-        let sel: u8 = match bank {
-            Bank::B0 => 0,
-            Bank::B1 => 1,
-            Bank::B2 => 2,
-        };
-        self.write(Register::BankSel, sel)
+    pub fn select_bank(&mut self, bank: Bank) -> Result<(), Error<E>> {
+        self.write(Register::BankSel, bank.sel())
+    }
+
+    /// Select driver current settings bank by its select code (0, 1 or 2)
+    pub fn select_bank_code(&mut self, code: u8) -> Result<(), Error<E>> {
+        let bank = Bank::from_sel(code).ok_or(Error::InvalidBank)?;
+        self.select_bank(bank)
+    }
+
+    /// Select `bank` only if at least one of its channels is lit, returns whether it was
+    ///
+    /// Lets a sequencer skip unprogrammed banks instead of flashing black.
+    pub fn select_if_programmed(&mut self, bank: Bank) -> Result<bool, Error<E>> {
+        if self.read_bank_raw(bank)? == Color::default() {
+            return Ok(false);
+        }
+        self.select_bank(bank)?;
+        Ok(true)
+    }
+
+    /// Get selected driver current settings bank, `None` for an invalid select code
+    pub fn selected_bank(&mut self) -> Result<Option<Bank>, Error<E>> {
+        let x = self.read(Register::BankSel)?;
+        Ok(Bank::from_sel(x))
+    }
+
+    /// Select `bank` while running `f`, then reselect the previously selected bank
+    ///
+    /// The previous selection is restored also when `f` fails; the error of `f` takes
+    /// precedence over one from restoring.
+    pub fn with_bank<R>(
+        &mut self,
+        bank: Bank,
+        f: impl FnOnce(&mut Self) -> Result<R, Error<E>>,
+    ) -> Result<R, Error<E>> {
+        let original = self.read(Register::BankSel)?;
+        self.select_bank(bank)?;
+        let res = f(self);
+        let restored = self.write(Register::BankSel, original);
+        let r = res?;
+        restored.map(|_| r)
     }
 
     /// Set master fader (Ctrl.mfe must be set)
-    pub fn set_fader(&mut self, fade: u8) -> Result<(), E> {
+    pub fn set_fader(&mut self, fade: u8) -> Result<(), Error<E>> {
         self.write(Register::Fader, fade)
     }
 
+    /// Set master fader, failing if fader control is disabled in the control register
+    pub fn set_fader_checked(&mut self, fade: u8) -> Result<(), Error<E>> {
+        let ctrl = self.get_ctrl()?;
+        if !ctrl.mfe() && !ctrl.pwm() {
+            return Err(Error::FaderDisabled);
+        }
+        self.set_fader(fade)
+    }
+
+    /// Set master fader, enabling Ctrl.mfe first if neither mfe nor pwm is set
+    ///
+    /// Unlike [`LM3549::set_fader`] the level takes effect immediately, at the cost of a
+    /// read-modify-write of the control register.
+    pub fn set_fader_auto(&mut self, fade: u8) -> Result<(), Error<E>> {
+        let mut ctrl = self.get_ctrl()?;
+        if !ctrl.mfe() && !ctrl.pwm() {
+            ctrl.set_mfe(true);
+            self.set_ctrl(ctrl)?;
+        }
+        self.set_fader(fade)
+    }
+
+    /// Step the master fader one level toward `target` once the fade interval has passed
+    ///
+    /// Never blocks, call it repeatedly from a main loop. Returns `true` once the fader is at
+    /// `target`.
+    #[cfg(feature = "effects")]
+    pub fn fade_step<C: Clock>(&mut self, target: u8, clock: &C) -> Result<bool, Error<E>> {
+        if self.fader_level()? == target {
+            return Ok(true);
+        }
+        let now = clock.now_ms();
+        if let Some(last) = self.fade_last_ms {
+            if now.wrapping_sub(last) < self.fade_interval_ms as u32 {
+                return Ok(false);
+            }
+        }
+        self.fade_last_ms = Some(now);
+        self.fade_to_step(target)
+    }
+
+    /// Last fader level written, read from the device if not known yet
+    pub(crate) fn fader_level(&mut self) -> Result<u8, Error<E>> {
+        match self.fader {
+            Some(level) => Ok(level),
+            None => {
+                let level = self.read(Register::Fader)?;
+                self.fader = Some(level);
+                Ok(level)
+            }
+        }
+    }
+
+    /// Step the master fader one level toward `target`
+    ///
+    /// Single transaction once the fader level is known to the driver, i.e. after any fader
+    /// write through it. Returns `true` once the fader is at `target`.
+    pub fn fade_to_step(&mut self, target: u8) -> Result<bool, Error<E>> {
+        let level = self.fader_level()?;
+        if level == target {
+            return Ok(true);
+        }
+        let next = if level < target { level + 1 } else { level - 1 };
+        self.set_fader(next)?;
+        Ok(next == target)
+    }
+
+    /// Fade the master fader to `target`, one level every `step_ms`
+    ///
+    /// Multiple transactions, use [`LM3549::fade_to_step`] to release a shared bus between
+    /// steps.
+    pub fn fade_to<D: DelayMs<u16>>(
+        &mut self,
+        target: u8,
+        step_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        while !self.fade_to_step(target)? {
+            delay.delay_ms(step_ms);
+        }
+        Ok(())
+    }
+
+    /// Fade the master fader to `target` like [`LM3549::fade_to`], giving up after `timeout`
+    ///
+    /// The timer is checked between fader steps; on timeout the fader is left at the level
+    /// reached so far. A single bus transaction that never returns cannot be bounded.
+    pub fn fade_to_timed<D: DelayMs<u16>, T: CountDown>(
+        &mut self,
+        target: u8,
+        step_ms: u16,
+        delay: &mut D,
+        timer: &mut T,
+        timeout: T::Time,
+    ) -> Result<(), Error<E>> {
+        timer.start(timeout);
+        while !self.fade_to_step(target)? {
+            if timer.wait().is_ok() {
+                return Err(Error::Timeout);
+            }
+            delay.delay_ms(step_ms);
+        }
+        Ok(())
+    }
+
+    /// Write step `step` of `steps` of a linear fade from `from` to `to` to a bank
+    ///
+    /// Single transaction. Step 0 writes `from` and step `steps` writes `to`.
+    pub fn cross_fade_step(
+        &mut self,
+        bank: Bank,
+        from: Color,
+        to: Color,
+        step: u16,
+        steps: u16,
+    ) -> Result<(), Error<E>> {
+        let c = if steps == 0 {
+            to
+        } else {
+            from.lerp(to, step.min(steps), steps)
+        };
+        self.write_bank_color(bank, c)
+    }
+
+    /// Fade a bank linearly from `from` to `to` in `steps` steps `step_ms` apart
+    ///
+    /// Multiple transactions, use [`LM3549::cross_fade_step`] to release a shared bus between
+    /// steps.
+    pub fn cross_fade<D: DelayMs<u16>>(
+        &mut self,
+        bank: Bank,
+        from: Color,
+        to: Color,
+        steps: u16,
+        step_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        for step in 0..steps {
+            self.cross_fade_step(bank, from, to, step, steps)?;
+            delay.delay_ms(step_ms);
+        }
+        self.cross_fade_step(bank, from, to, steps, steps)
+    }
+
+    /// Read the effective master fader level
+    ///
+    /// With `Ctrl.mfe` set this is the level the outputs are dimmed by. The Fader register
+    /// does not track the PWM input, so with `Ctrl.pwm` set this returns the last value
+    /// written to the register rather than the measured PWM duty.
+    pub fn read_effective_fade(&mut self) -> Result<u8, Error<E>> {
+        self.read(Register::Fader)
+    }
+
+    /// Breathe the master fader between `min` and `max` (Ctrl.mfe must be set)
+    ///
+    /// Every cycle takes [`BREATHE_STEPS`] fader writes paced `period_ms / BREATHE_STEPS`
+    /// apart. A final write leaves the fader at `max`.
+    #[cfg(feature = "effects")]
+    pub fn breathe<D: DelayMs<u16>>(
+        &mut self,
+        period_ms: u16,
+        cycles: u16,
+        min: u8,
+        max: u8,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        let steps = Breathe::new(min, max, cycles).ok_or(Error::InvalidRange)?;
+        let step_ms = period_ms / BREATHE_STEPS;
+        for level in steps {
+            self.set_fader(level)?;
+            delay.delay_ms(step_ms);
+        }
+        Ok(())
+    }
+
+    /// Strobe the outputs `pulses` times, paced by a hardware timer (Ctrl.mfe must be set)
+    ///
+    /// Each pulse drives the fader to its current level for `on` and to zero for `off`. The
+    /// timer bounds the pulse timing, leaving the fader write itself as the only source of
+    /// jitter. The fader level is restored afterwards, also when a write fails.
+    #[cfg(feature = "effects")]
+    pub fn strobe_with_timer<T>(
+        &mut self,
+        on: T::Time,
+        off: T::Time,
+        pulses: u16,
+        timer: &mut T,
+    ) -> Result<(), Error<E>>
+    where
+        T: CountDown,
+        T::Time: Copy,
+    {
+        let level = self.read(Register::Fader)?;
+        let mut strobe = || -> Result<(), Error<E>> {
+            for _ in 0..pulses {
+                self.set_fader(level)?;
+                timer.start(on);
+                while timer.wait().is_err() {}
+                self.set_fader(0)?;
+                timer.start(off);
+                while timer.wait().is_err() {}
+            }
+            Ok(())
+        };
+        let res = strobe();
+        self.set_fader(level)?;
+        res
+    }
+
+    /// Flicker the master fader for `steps` steps `step_ms` apart (Ctrl.mfe must be set)
+    #[cfg(all(feature = "effects", feature = "rand_core"))]
+    pub fn run_flicker<R: rand_core::RngCore, D: DelayMs<u16>>(
+        &mut self,
+        flicker: &mut Flicker,
+        rng: &mut R,
+        steps: u16,
+        step_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        for _ in 0..steps {
+            self.set_fader(flicker.next_level(rng))?;
+            delay.delay_ms(step_ms);
+        }
+        Ok(())
+    }
+
     /// Set control register
-    pub fn set_ctrl(&mut self, ctrl: Ctrl) -> Result<(), E> {
+    pub fn set_ctrl(&mut self, ctrl: Ctrl) -> Result<(), Error<E>> {
         self.write(Register::Ctrl, ctrl.0)
     }
 
+    /// Get control register
+    pub fn get_ctrl(&mut self) -> Result<Ctrl, Error<E>> {
+        let x = self.read(Register::Ctrl)?;
+        Ok(Ctrl(x))
+    }
+
+    /// Temporarily set the control register
+    ///
+    /// The previous control register is restored when the returned guard is dropped.
+    /// Errors while restoring from `Drop` are ignored, use [`CtrlGuard::finish`] to see them.
+    pub fn modify_ctrl_scoped(&mut self, ctrl: Ctrl) -> Result<CtrlGuard<'_, I2C>, Error<E>> {
+        let original = self.get_ctrl()?;
+        self.set_ctrl(ctrl)?;
+        Ok(CtrlGuard {
+            dev: self,
+            original,
+            restored: false,
+        })
+    }
+
+    /// Set bits of the control register in `mask`, leaving the others as they are
+    pub fn set_ctrl_bits(&mut self, mask: u8) -> Result<(), Error<E>> {
+        let ctrl = self.get_ctrl()?;
+        self.set_ctrl(Ctrl(ctrl.0 | mask))
+    }
+
+    /// Clear bits of the control register in `mask`, leaving the others as they are
+    pub fn clear_ctrl_bits(&mut self, mask: u8) -> Result<(), Error<E>> {
+        let ctrl = self.get_ctrl()?;
+        self.set_ctrl(Ctrl(ctrl.0 & !mask))
+    }
+
     /// Set current limit register
-    pub fn set_ilimit(&mut self, limit: Ilimit) -> Result<(), E> {
+    pub fn set_ilimit(&mut self, limit: Ilimit) -> Result<(), Error<E>> {
         self.write(Register::Ilimit, limit.0)
     }
 
+    /// Set current limit register, rejecting grossly mismatched positive and negative limits
+    pub fn set_ilimit_checked(&mut self, limit: Ilimit) -> Result<(), Error<E>> {
+        if limit.is_asymmetric() {
+            return Err(Error::AsymmetricIlimit);
+        }
+        self.set_ilimit(limit)
+    }
+
+    /// Wait for the current EEPROM operation to finish
+    #[cfg(feature = "eeprom")]
+    fn eeprom_wait(&mut self) -> Result<(), Error<E>> {
+        for _ in 0..EEPROM_POLL_LIMIT {
+            let ctrl = EepromCtrl(self.read(Register::EepromCtrl)?);
+            if ctrl.ready() {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Wait for the current EEPROM operation to finish within `timeout` of `timer`
+    #[cfg(feature = "eeprom")]
+    fn eeprom_wait_timed<T: CountDown>(
+        &mut self,
+        timer: &mut T,
+        timeout: T::Time,
+    ) -> Result<(), Error<E>> {
+        timer.start(timeout);
+        loop {
+            let ctrl = EepromCtrl(self.read(Register::EepromCtrl)?);
+            if ctrl.ready() {
+                return Ok(());
+            }
+            if timer.wait().is_ok() {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+
+    /// Program the configuration registers into EEPROM
+    #[cfg(feature = "eeprom")]
+    pub fn eeprom_store(&mut self) -> Result<(), Error<E>> {
+        self.eeprom_start_store()?;
+        self.eeprom_wait()
+    }
+
+    /// Program the configuration registers into EEPROM, giving up after `timeout`
+    ///
+    /// See [`LM3549::eeprom_restore_timed`] for what the timeout covers. Programming
+    /// continues on the device after a timeout and cannot be aborted.
+    #[cfg(feature = "eeprom")]
+    pub fn eeprom_store_timed<T: CountDown>(
+        &mut self,
+        timer: &mut T,
+        timeout: T::Time,
+    ) -> Result<(), Error<E>> {
+        self.eeprom_start_store()?;
+        self.eeprom_wait_timed(timer, timeout)
+    }
+
+    #[cfg(feature = "eeprom")]
+    fn eeprom_start_store(&mut self) -> Result<(), Error<E>> {
+        let mut ctrl = EepromCtrl(0);
+        ctrl.set_prog(true);
+        self.write(Register::EepromCtrl, ctrl.0)
+    }
+
+    /// Load the configuration registers from EEPROM
+    #[cfg(feature = "eeprom")]
+    pub fn eeprom_restore(&mut self) -> Result<(), Error<E>> {
+        self.eeprom_start_restore()?;
+        self.eeprom_wait()
+    }
+
+    /// Load the configuration registers from EEPROM, giving up after `timeout`
+    ///
+    /// The timer is checked between the status polls, so this bounds a device that never
+    /// reports ready. It cannot bound a single bus transaction that never returns, blocking
+    /// HALs offer no way to interrupt those.
+    #[cfg(feature = "eeprom")]
+    pub fn eeprom_restore_timed<T: CountDown>(
+        &mut self,
+        timer: &mut T,
+        timeout: T::Time,
+    ) -> Result<(), Error<E>> {
+        self.eeprom_start_restore()?;
+        self.eeprom_wait_timed(timer, timeout)
+    }
+
+    #[cfg(feature = "eeprom")]
+    fn eeprom_start_restore(&mut self) -> Result<(), Error<E>> {
+        // The restore reloads the fader behind the driver's back
+        self.fader = None;
+        #[cfg(feature = "effects")]
+        {
+            self.fade_last_ms = None;
+        }
+        let mut ctrl = EepromCtrl(0);
+        ctrl.set_init(true);
+        self.write(Register::EepromCtrl, ctrl.0)
+    }
+
+    /// Program the configuration registers into EEPROM and verify them
+    ///
+    /// Reloads the EEPROM after programming and compares every configuration register with
+    /// its value before programming.
+    #[cfg(feature = "eeprom")]
+    pub fn eeprom_store_verified(&mut self) -> Result<(), Error<E>> {
+        let mut expected = [0u8; CONFIG_LEN];
+        self.read_registers(Register::BankSel, &mut expected)?;
+        self.eeprom_store()?;
+        self.eeprom_restore()?;
+        let mut actual = [0u8; CONFIG_LEN];
+        self.read_registers(Register::BankSel, &mut actual)?;
+        for (address, (&expected, &actual)) in expected.iter().zip(actual.iter()).enumerate() {
+            if expected != actual {
+                return Err(Error::VerifyMismatch {
+                    register: Register::try_from(address as u8).unwrap_or(Register::BankSel),
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Set fault mask register
-    pub fn set_fault_mask(&mut self, mask: FaultMask) -> Result<(), E> {
+    pub fn set_fault_mask(&mut self, mask: FaultMask) -> Result<(), Error<E>> {
         self.write(Register::FaultMask, mask.0)
     }
 }
+
+/// Single-transaction reads for buses supporting [`i2c::Transactional`]
+///
+/// These send the register address and read the data without releasing the bus in between.
+/// The methods without the `_tx` suffix use separate write and read transactions and work
+/// with any bus.
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Transactional<Error = E>,
+{
+    /// Read a register in one transaction
+    pub fn read_tx(&mut self, register: Register) -> Result<u8, Error<E>> {
+        let mut buf = [0u8; 1];
+        self.read_registers_tx(register, &mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Read consecutive registers starting at `start` in one transaction
+    pub fn read_registers_tx(&mut self, start: Register, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.bus_read_tx(start as u8, buf)
+    }
+
+    /// Read the register file from BankSel to User2, one transaction per contiguous block
+    pub fn dump_registers_tx(&mut self) -> Result<RegisterDump, Error<E>> {
+        let (mut config, mut user) = ([0u8; 0x18], [0u8; 2]);
+        self.read_registers_tx(Register::BankSel, &mut config)?;
+        self.read_registers_tx(Register::User1, &mut user)?;
+        Ok(RegisterDump::from_reads(config, user))
+    }
+}
+
+/// Restores the control register when dropped, see [`LM3549::modify_ctrl_scoped`]
+pub struct CtrlGuard<'a, I2C>
+where
+    I2C: i2c::Write + i2c::Read<Error = <I2C as i2c::Write>::Error>,
+{
+    dev: &'a mut LM3549<I2C>,
+    original: Ctrl,
+    restored: bool,
+}
+
+impl<'a, I2C> CtrlGuard<'a, I2C>
+where
+    I2C: i2c::Write + i2c::Read<Error = <I2C as i2c::Write>::Error>,
+{
+    /// Restore the control register and report the result
+    pub fn finish(mut self) -> Result<(), Error<<I2C as i2c::Write>::Error>> {
+        self.restored = true;
+        self.dev.set_ctrl(self.original)
+    }
+}
+
+impl<'a, I2C> Deref for CtrlGuard<'a, I2C>
+where
+    I2C: i2c::Write + i2c::Read<Error = <I2C as i2c::Write>::Error>,
+{
+    type Target = LM3549<I2C>;
+
+    fn deref(&self) -> &LM3549<I2C> {
+        self.dev
+    }
+}
+
+impl<'a, I2C> DerefMut for CtrlGuard<'a, I2C>
+where
+    I2C: i2c::Write + i2c::Read<Error = <I2C as i2c::Write>::Error>,
+{
+    fn deref_mut(&mut self) -> &mut LM3549<I2C> {
+        self.dev
+    }
+}
+
+impl<'a, I2C> Drop for CtrlGuard<'a, I2C>
+where
+    I2C: i2c::Write + i2c::Read<Error = <I2C as i2c::Write>::Error>,
+{
+    fn drop(&mut self) {
+        if !self.restored {
+            let _ = self.dev.set_ctrl(self.original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+
+    #[test]
+    #[cfg(feature = "eeprom")]
+    fn eeprom_restore_drops_cached_fader() {
+        let (mut dev, model) = Model::driver();
+        model.0.borrow_mut().eeprom[Register::Fader as usize] = 0x80;
+        dev.set_fader(0x10).unwrap();
+        dev.eeprom_restore().unwrap();
+        assert_eq!(model.reg(Register::Fader), 0x80);
+        assert!(!dev.fade_to_step(0x90).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x81);
+    }
+
+    #[test]
+    fn channel_order_maps_logical_to_physical() {
+        let (mut dev, model) = Model::driver();
+        dev.set_channel_order([Channel::Green, Channel::Blue, Channel::Red])
+            .unwrap();
+        let c = Color {
+            r: 0x123,
+            g: 0x045,
+            b: 0x006,
+        };
+        dev.write_bank_color(Bank::B0, c).unwrap();
+        assert_eq!(model.reg(Register::Ig0Lsb), 0x23);
+        assert_eq!(model.reg(Register::Ig0Msb), 0x01);
+        assert_eq!(model.reg(Register::Ib0Lsb), 0x45);
+        assert_eq!(model.reg(Register::Ir0Lsb), 0x06);
+        assert_eq!(dev.read_bank(Bank::B0).unwrap(), c);
+    }
+
+    #[test]
+    fn channel_order_rejects_repeated_channel() {
+        let (mut dev, _) = Model::driver();
+        assert!(matches!(
+            dev.set_channel_order([Channel::Red, Channel::Red, Channel::Blue]),
+            Err(Error::InvalidChannelOrder)
+        ));
+    }
+
+    #[test]
+    fn new_address_is_used() {
+        let (mut dev, model) = Model::driver();
+        dev.set_address(0x41).unwrap();
+        dev.get_ctrl().unwrap();
+        assert!(model.ops().iter().all(|op| match op {
+            testing::Op::Write(address, _) | testing::Op::Read(address, _) => *address == 0x41,
+        }));
+        assert!(matches!(
+            dev.set_address(0x80),
+            Err(Error::InvalidAddress(0x80))
+        ));
+        assert_eq!(dev.address(), 0x41);
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    fn breathe_ends_at_max() {
+        let (mut dev, model) = Model::driver();
+        let mut delay = embedded_hal_mock::delay::MockNoop::new();
+        dev.breathe(640, 2, 0x10, 0xF0, &mut delay).unwrap();
+        assert_eq!(model.reg(Register::Fader), 0xF0);
+        let writes = model.writes();
+        assert!(writes.len() <= 2 * BREATHE_STEPS as usize + 1);
+        assert!(writes
+            .iter()
+            .all(|w| w[0] == Register::Fader as u8 && (0x10..=0xF0).contains(&w[1])));
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    fn breathe_rejects_inverted_range() {
+        let (mut dev, model) = Model::driver();
+        let mut delay = embedded_hal_mock::delay::MockNoop::new();
+        assert!(matches!(
+            dev.breathe(640, 1, 0xF0, 0x10, &mut delay),
+            Err(Error::InvalidRange)
+        ));
+        assert!(model.ops().is_empty());
+    }
+
+    #[test]
+    fn effective_fade_reads_fader_register() {
+        let (mut dev, model) = Model::driver();
+        let mut ctrl = Ctrl::default();
+        ctrl.set_pwm(true);
+        dev.set_ctrl(ctrl).unwrap();
+        model.set_reg(Register::Fader, 0x5A);
+        assert_eq!(dev.read_effective_fade().unwrap(), 0x5A);
+    }
+
+    #[test]
+    fn diagnostic_fault_cycle_restores_mask() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::FaultMask, 0x04);
+        model.set_reg(Register::Fault, 0x01);
+        assert!(dev.diagnostic_fault_cycle().unwrap().ocp());
+        assert_eq!(model.reg(Register::FaultMask), 0x04);
+        let masks = model
+            .writes()
+            .into_iter()
+            .filter(|w| w.len() == 2 && w[0] == Register::FaultMask as u8)
+            .map(|w| w[1]);
+        assert!(masks.eq([0x1F, 0x04]));
+    }
+
+    #[test]
+    fn diagnostic_fault_cycle_restores_mask_after_failed_read() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::FaultMask, 0x04);
+        model.fail_in(4);
+        assert!(dev.diagnostic_fault_cycle().is_err());
+        assert_eq!(model.reg(Register::FaultMask), 0x04);
+    }
+
+    #[test]
+    fn write_banks_safe_writes_selected_bank_last() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::BankSel, Bank::B1.sel());
+        dev.write_banks_safe(&[(1, 2, 3), (4, 5, 6), (7, 8, 9)])
+            .unwrap();
+        let order = model
+            .writes()
+            .into_iter()
+            .filter(|w| w.len() == 7)
+            .map(|w| w[0]);
+        assert!(order.eq([Bank::B0 as u8, Bank::B2 as u8, Bank::B1 as u8]));
+        assert_eq!(dev.read_bank(Bank::B1).unwrap(), Color::new(4, 5, 6));
+    }
+
+    #[test]
+    fn clear_zeroes_every_writable_register() {
+        let (mut dev, model) = Model::driver();
+        for addr in 0x00..=Register::FaultMask as usize {
+            model.0.borrow_mut().regs[addr] = 0xFF;
+        }
+        dev.clear().unwrap();
+        let regs = model.0.borrow().regs;
+        assert!(regs[..=Register::FaultMask as usize]
+            .iter()
+            .all(|&r| r == 0));
+    }
+
+    #[test]
+    fn clear_reports_partial_write() {
+        let (mut dev, model) = Model::driver();
+        model.fail_in(2);
+        assert!(matches!(
+            dev.clear(),
+            Err(Error::PartialWrite { written: 12, .. })
+        ));
+    }
+
+    #[test]
+    fn select_bank_code_checks_range() {
+        let (mut dev, model) = Model::driver();
+        dev.select_bank_code(0).unwrap();
+        assert_eq!(model.reg(Register::BankSel), 0);
+        dev.select_bank_code(2).unwrap();
+        assert_eq!(model.reg(Register::BankSel), 2);
+        model.clear_ops();
+        assert!(matches!(dev.select_bank_code(3), Err(Error::InvalidBank)));
+        assert!(model.ops().is_empty());
+    }
+
+    #[test]
+    fn current_budget_rejects_before_the_bus() {
+        let (mut dev, model) = Model::driver();
+        dev.set_range_policy(RangePolicy::Reject);
+        dev.set_current_budget_ma(1000);
+        let c = Color::from_ma(500, 400, 300);
+        assert!(matches!(
+            dev.write_bank_color(Bank::B0, c),
+            Err(Error::OverBudget { bank: Bank::B0, .. })
+        ));
+        assert!(model.ops().is_empty());
+        dev.write_bank_color(Bank::B0, Color::from_ma(500, 400, 0))
+            .unwrap();
+    }
+
+    #[test]
+    fn current_budget_scales_with_clamp_policy() {
+        let (mut dev, _) = Model::driver();
+        dev.set_range_policy(RangePolicy::Clamp);
+        dev.set_current_budget_ma(1000);
+        dev.write_bank_color(Bank::B0, Color::from_ma(500, 400, 300))
+            .unwrap();
+        let written = dev.read_bank(Bank::B0).unwrap();
+        assert!(written.total() * CURRENT_LSB_UA <= 1_000_000);
+        assert!(written.r > written.g && written.g > written.b);
+
+        dev.clear_current_budget();
+        dev.write_bank_color(Bank::B0, Color::from_ma(500, 400, 300))
+            .unwrap();
+        assert_eq!(
+            dev.read_bank(Bank::B0).unwrap(),
+            Color::from_ma(500, 400, 300)
+        );
+    }
+
+    #[test]
+    fn u16_pair_is_little_endian_and_masked() {
+        let (mut dev, model) = Model::driver();
+        dev.write_u16_pair(Register::Ig1Lsb, 0x2A5).unwrap();
+        assert_eq!(model.reg(Register::Ig1Lsb), 0xA5);
+        assert_eq!(model.reg(Register::Ig1Msb), 0x02);
+        dev.write_u16_pair(Register::Ig1Lsb, 0xFFFF).unwrap();
+        assert_eq!(model.reg(Register::Ig1Msb), 0x03);
+
+        model.set_reg(Register::Ib2Lsb, 0x34);
+        model.set_reg(Register::Ib2Msb, 0xFD);
+        assert_eq!(dev.read_u16_pair(Register::Ib2Lsb).unwrap(), 0x134);
+    }
+
+    #[test]
+    fn write_block_rejects_overlong_data() {
+        let (mut dev, model) = Model::driver();
+        assert!(matches!(
+            dev.write_block(Register::Ctrl, &[0; 4]),
+            Err(Error::BlockTooLong)
+        ));
+        assert!(matches!(
+            dev.write_block(Register::User2, &[0; 2]),
+            Err(Error::BlockTooLong)
+        ));
+        assert!(model.ops().is_empty());
+        dev.write_block(Register::Ctrl, &[0x11, 0x22, 0x33])
+            .unwrap();
+        assert_eq!(model.reg(Register::FaultMask), 0x33);
+    }
+
+    #[test]
+    fn configure_all_banks_is_one_transaction() {
+        let (mut dev, model) = Model::driver();
+        let banks = [
+            Color::new(0x3FF, 0, 0),
+            Color::new(0, 0x155, 0),
+            Color::new(0, 0, 0x2AA),
+        ];
+        dev.configure_all_banks(banks).unwrap();
+        let writes = model.writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].len(), 19);
+        assert_eq!(writes[0][0], Register::Ir0Lsb as u8);
+        for (bank, c) in Bank::iter().zip(banks.iter()) {
+            assert_eq!(dev.read_bank(bank).unwrap(), *c);
+        }
+    }
+
+    #[test]
+    fn configure_all_banks_validates_before_writing() {
+        let (mut dev, model) = Model::driver();
+        dev.set_range_policy(RangePolicy::Reject);
+        let mut banks = [Color::default(); 3];
+        banks[2].g = 0x400;
+        assert!(matches!(
+            dev.configure_all_banks(banks),
+            Err(Error::CodeOutOfRange {
+                bank: Bank::B2,
+                channel: Channel::Green,
+                code: 0x400
+            })
+        ));
+        assert!(model.ops().is_empty());
+    }
+
+    /// Timer expiring once it has been waited on as often as its count
+    struct Timer(u32);
+
+    impl CountDown for Timer {
+        type Time = u32;
+
+        fn start<T: Into<u32>>(&mut self, count: T) {
+            self.0 = count.into();
+        }
+
+        fn wait(&mut self) -> nb::Result<(), void::Void> {
+            if self.0 == 0 {
+                return Ok(());
+            }
+            self.0 -= 1;
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "eeprom")]
+    fn eeprom_restore_timed_gives_up() {
+        let (mut dev, model) = Model::driver();
+        model.0.borrow_mut().eeprom_stuck = true;
+        assert!(matches!(
+            dev.eeprom_restore_timed(&mut Timer(0), 3),
+            Err(Error::Timeout)
+        ));
+        let polls = model
+            .ops()
+            .into_iter()
+            .filter(|op| matches!(op, testing::Op::Read(..)))
+            .count();
+        assert_eq!(polls, 4);
+
+        model.0.borrow_mut().eeprom_stuck = false;
+        dev.eeprom_store_timed(&mut Timer(0), 3).unwrap();
+    }
+
+    #[test]
+    fn fade_to_timed_leaves_fader_where_it_stopped() {
+        let (mut dev, model) = Model::driver();
+        let mut delay = embedded_hal_mock::delay::MockNoop::new();
+        assert!(matches!(
+            dev.fade_to_timed(0x80, 1, &mut delay, &mut Timer(0), 4),
+            Err(Error::Timeout)
+        ));
+        let level = model.reg(Register::Fader);
+        assert!(level > 0 && level < 0x80);
+        dev.fade_to_timed(0x80, 1, &mut delay, &mut Timer(0), 1000)
+            .unwrap();
+        assert_eq!(model.reg(Register::Fader), 0x80);
+    }
+
+    #[test]
+    #[cfg(feature = "names")]
+    fn error_display_carries_context() {
+        extern crate std;
+        use std::string::ToString;
+
+        let (mut dev, model) = Model::driver();
+        model.fail_in(0);
+        let e = dev.set_ctrl(Ctrl::default()).unwrap_err();
+        assert_eq!(e.to_string(), "I2C bus error on write of Ctrl (0x14)");
+
+        let e: Error<testing::BusError> = Error::CodeOutOfRange {
+            bank: Bank::B1,
+            channel: Channel::Blue,
+            code: 0x412,
+        };
+        assert_eq!(
+            e.to_string(),
+            "current code 0x412 out of range for channel Blue in bank B1"
+        );
+
+        let e: Error<testing::BusError> = Error::VerifyMismatch {
+            register: Register::Ilimit,
+            expected: 0x22,
+            actual: 0x11,
+        };
+        assert_eq!(
+            e.to_string(),
+            "verify failed for Ilimit (0x15): wrote 0x22, read 0x11"
+        );
+    }
+
+    #[test]
+    fn write_banks_safe_reports_progress() {
+        let (mut dev, model) = Model::driver();
+        let colors = [(1, 2, 3), (4, 5, 6), (7, 8, 9)];
+        model.fail_in(3);
+        assert!(matches!(
+            dev.write_banks_safe(&colors),
+            Err(Error::PartialWrite {
+                written: 6,
+                operation: Operation {
+                    register: Some(Register::Ir2Lsb),
+                    kind: OpKind::BlockWrite,
+                },
+                ..
+            })
+        ));
+
+        model.fail_in(2);
+        assert!(matches!(dev.write_banks_safe(&colors), Err(Error::I2c(..))));
+    }
+
+    #[test]
+    fn select_if_programmed_skips_blank_bank() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::BankSel, Bank::B0.sel());
+        model.set_reg(Register::Ib2Msb, 0x01);
+        assert!(!dev.select_if_programmed(Bank::B1).unwrap());
+        assert_eq!(model.reg(Register::BankSel), Bank::B0.sel());
+        assert!(dev.select_if_programmed(Bank::B2).unwrap());
+        assert_eq!(model.reg(Register::BankSel), Bank::B2.sel());
+    }
+
+    #[test]
+    #[cfg(feature = "eeprom")]
+    fn eeprom_store_verified_checks_the_reload() {
+        let (mut dev, model) = Model::driver();
+        dev.set_fader(0x55).unwrap();
+        dev.eeprom_store_verified().unwrap();
+        assert_eq!(model.0.borrow().eeprom[Register::Fader as usize], 0x55);
+
+        model.0.borrow_mut().eeprom_dead = Some(Register::Fader as u8);
+        dev.set_fader(0x66).unwrap();
+        assert!(matches!(
+            dev.eeprom_store_verified(),
+            Err(Error::VerifyMismatch {
+                register: Register::Fader,
+                expected: 0x66,
+                actual: 0x55,
+            })
+        ));
+
+        model.0.borrow_mut().eeprom_stuck = true;
+        assert!(matches!(dev.eeprom_store_verified(), Err(Error::Timeout)));
+    }
+
+    #[cfg(feature = "effects")]
+    struct TestClock(core::cell::Cell<u32>);
+
+    #[cfg(feature = "effects")]
+    impl Clock for TestClock {
+        fn now_ms(&self) -> u32 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    fn fade_step_steps_once_per_interval() {
+        let (mut dev, model) = Model::driver();
+        dev.set_fade_interval(10);
+        dev.set_fader(0x10).unwrap();
+        let clock = TestClock(core::cell::Cell::new(1000));
+
+        assert!(!dev.fade_step(0x13, &clock).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x11);
+        clock.0.set(1009);
+        assert!(!dev.fade_step(0x13, &clock).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x11);
+        clock.0.set(1010);
+        assert!(!dev.fade_step(0x13, &clock).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x12);
+        clock.0.set(1020);
+        assert!(dev.fade_step(0x13, &clock).unwrap());
+        assert_eq!(model.reg(Register::Fader), 0x13);
+
+        model.clear_ops();
+        clock.0.set(1030);
+        assert!(dev.fade_step(0x13, &clock).unwrap());
+        assert!(model.ops().is_empty());
+    }
+
+    #[test]
+    fn tx_reads_use_one_transaction_per_block() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Fader, 0x5A);
+        assert_eq!(dev.read_tx(Register::Fader).unwrap(), 0x5A);
+        assert_eq!(model.0.borrow().execs, 1);
+        assert!(model.ops().into_iter().eq([
+            testing::Op::Write(0x36, [Register::Fader as u8].to_vec()),
+            testing::Op::Read(0x36, [0x5A].to_vec()),
+        ]));
+
+        model.set_reg(Register::User2, 0xA5);
+        let dump = dev.dump_registers_tx().unwrap();
+        assert_eq!(dump.get(Register::Fader), Some(0x5A));
+        assert_eq!(dump.get(Register::User2), Some(0xA5));
+        assert_eq!(model.0.borrow().execs, 3);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fault_error_source_chain_ends_at_the_bus_error() {
+        use std::error::Error as _;
+        use std::string::ToString;
+
+        let (mut dev, model) = Model::driver();
+        dev.check_fault().unwrap();
+
+        model.set_reg(Register::Fault, 0x01);
+        let e = dev.check_fault().unwrap_err();
+        assert!(matches!(e, FaultError::Device(f) if f.0 == 0x01));
+        assert!(e.source().is_none());
+
+        model.fail_in(1);
+        let e = dev.check_fault().unwrap_err();
+        assert_eq!(e.to_string(), "fault register read failed");
+        let driver = e.source().unwrap();
+        assert_eq!(driver.to_string(), "I2C bus error on read of Fault (0x17)");
+        let bus = driver.source().unwrap();
+        assert!(bus.is::<testing::BusError>());
+        assert!(bus.source().is_none());
+    }
+
+    #[test]
+    fn step_methods_are_single_transactions() {
+        let (mut dev, model) = Model::driver();
+        dev.set_fader(0x10).unwrap();
+        model.clear_ops();
+        assert!(!dev.fade_to_step(0x12).unwrap());
+        assert!(model.ops().into_iter().eq([testing::Op::Write(
+            0x36,
+            [Register::Fader as u8, 0x11].to_vec()
+        )]));
+
+        let to = Color::new(0x100, 0x200, 0x300);
+        model.clear_ops();
+        dev.cross_fade_step(Bank::B1, Color::default(), to, 1, 4)
+            .unwrap();
+        let mut bank = [Bank::B1 as u8; 7];
+        bank[1..].copy_from_slice(&Color::new(0x40, 0x80, 0xC0).to_le_bytes());
+        assert!(model
+            .ops()
+            .into_iter()
+            .eq([testing::Op::Write(0x36, bank.to_vec())]));
+    }
+
+    #[test]
+    fn fades_are_one_transaction_per_step() {
+        let (mut dev, model) = Model::driver();
+        dev.set_fader(0x10).unwrap();
+        model.clear_ops();
+        dev.fade_to(0x14, 1, &mut embedded_hal_mock::delay::MockNoop::new())
+            .unwrap();
+        let faders = model.writes().into_iter().map(|w| w[1]);
+        assert!(faders.eq([0x11, 0x12, 0x13, 0x14]));
+        assert_eq!(model.ops().len(), 4);
+
+        let to = Color::new(0x100, 0x200, 0x300);
+        model.clear_ops();
+        dev.cross_fade(
+            Bank::B2,
+            Color::default(),
+            to,
+            4,
+            1,
+            &mut embedded_hal_mock::delay::MockNoop::new(),
+        )
+        .unwrap();
+        assert_eq!(model.ops().len(), 5);
+        assert!(model.writes().iter().all(|w| w[0] == Bank::B2 as u8));
+        assert_eq!(dev.read_bank(Bank::B2).unwrap(), to);
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    fn strobe_with_timer_restores_the_fader() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Fader, 0x80);
+        dev.strobe_with_timer(2, 3, 2, &mut Timer(0)).unwrap();
+        let faders = model
+            .writes()
+            .into_iter()
+            .filter(|w| w.len() == 2)
+            .map(|w| w[1]);
+        assert!(faders.eq([0x80, 0x00, 0x80, 0x00, 0x80]));
+        assert_eq!(model.reg(Register::Fader), 0x80);
+
+        model.clear_ops();
+        // The second pulse fails with the fader off
+        model.fail_in(4);
+        assert!(dev.strobe_with_timer(2, 3, 2, &mut Timer(0)).is_err());
+        assert_eq!(model.ops().len(), 6);
+        assert_eq!(model.reg(Register::Fader), 0x80);
+    }
+
+    /// Timer recording every period started, expiring at once
+    #[derive(Default)]
+    struct Cadence {
+        periods: [u32; 8],
+        len: usize,
+    }
+
+    impl CountDown for Cadence {
+        type Time = u32;
+
+        fn start<T: Into<u32>>(&mut self, count: T) {
+            self.periods[self.len] = count.into();
+            self.len += 1;
+        }
+
+        fn wait(&mut self) -> nb::Result<(), void::Void> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    fn strobe_with_timer_paces_each_pulse() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Fader, 0x40);
+        let mut timer = Cadence::default();
+        dev.strobe_with_timer(20, 80, 3, &mut timer).unwrap();
+        assert_eq!(timer.periods[..timer.len], [20, 80, 20, 80, 20, 80]);
+        let pulses = model
+            .writes()
+            .into_iter()
+            .filter(|w| w[..] == [Register::Fader as u8, 0x00])
+            .count();
+        assert_eq!(pulses, 3);
+
+        let mut timer = Cadence::default();
+        model.clear_ops();
+        dev.strobe_with_timer(20, 80, 0, &mut timer).unwrap();
+        assert_eq!(timer.len, 0);
+        assert_eq!(model.writes().len(), 2);
+    }
+
+    #[test]
+    fn bank_load_sums_the_codes() {
+        let (mut dev, model) = Model::driver();
+        assert_eq!(
+            Color::new(0x100, 0x200, MAX_CODE).total(),
+            0x100 + 0x200 + 0x3FF
+        );
+        dev.write_bank_color(Bank::B2, Color::new(0x123, 0x3FF, 0x001))
+            .unwrap();
+        assert_eq!(dev.bank_load(Bank::B2).unwrap(), 0x123 + 0x3FF + 0x001);
+        assert_eq!(dev.bank_load(Bank::B0).unwrap(), 0);
+
+        model.set_reg(Register::Ir1Msb, 0xFC);
+        assert_eq!(dev.bank_load(Bank::B1).unwrap(), 0);
+    }
+
+    #[test]
+    fn ctrl_guard_restores_on_drop_and_finish() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Ctrl, 0x12);
+        {
+            let mut guard = dev.modify_ctrl_scoped(Ctrl(0x04)).unwrap();
+            assert_eq!(model.reg(Register::Ctrl), 0x04);
+            guard.set_fader(0x20).unwrap();
+        }
+        assert_eq!(model.reg(Register::Ctrl), 0x12);
+        assert_eq!(model.reg(Register::Fader), 0x20);
+
+        let guard = dev.modify_ctrl_scoped(Ctrl(0x04)).unwrap();
+        model.clear_ops();
+        model.fail_in(0);
+        assert!(guard.finish().is_err());
+        assert_eq!(model.ops().len(), 1);
+        assert_eq!(model.reg(Register::Ctrl), 0x04);
+    }
+
+    #[test]
+    fn read_all_banks_returns_each_bank() {
+        use core::convert::TryFrom;
+
+        let (mut dev, model) = Model::driver();
+        let colors = [
+            Color::new(0x001, 0x002, 0x003),
+            Color::new(0x100, 0x200, 0x300),
+            Color::new(MAX_CODE, 0x000, 0x155),
+        ];
+        for (bank, c) in Bank::ALL.iter().zip(colors.iter()) {
+            let data = c.to_le_bytes();
+            for (i, &value) in data.iter().enumerate() {
+                let register = Register::try_from(*bank as u8 + i as u8).unwrap();
+                model.set_reg(register, value);
+            }
+        }
+        assert_eq!(dev.read_all_banks().unwrap(), colors);
+    }
+
+    #[test]
+    fn every_bank_passes_the_address_check() {
+        let (mut dev, model) = Model::driver();
+        for &bank in Bank::ALL.iter() {
+            dev.write_bank_color(bank, Color::new(1, 2, 3)).unwrap();
+            assert_eq!(model.writes().last().unwrap()[0], bank as u8);
+            assert_eq!(dev.read_bank(bank).unwrap(), Color::new(1, 2, 3));
+        }
+    }
+
+    #[test]
+    fn set_fader_auto_enables_mfe_once() {
+        let (mut dev, model) = Model::driver();
+        dev.set_fader_auto(0x40).unwrap();
+        assert!(Ctrl(model.reg(Register::Ctrl)).mfe());
+        assert_eq!(model.reg(Register::Fader), 0x40);
+        let writes = model.writes();
+        let values = writes.iter().filter(|w| w.len() == 2);
+        assert!(values.eq([
+            [Register::Ctrl as u8, 0x02].to_vec(),
+            [Register::Fader as u8, 0x40].to_vec(),
+        ]
+        .iter()));
+
+        model.clear_ops();
+        dev.set_fader_auto(0x50).unwrap();
+        assert!(model
+            .writes()
+            .iter()
+            .filter(|w| w.len() == 2)
+            .eq([[Register::Fader as u8, 0x50].to_vec()].iter()));
+
+        let (mut dev, model) = Model::driver();
+        let mut pwm = Ctrl(0);
+        pwm.set_pwm(true);
+        model.set_reg(Register::Ctrl, pwm.0);
+        dev.set_fader_auto(0x60).unwrap();
+        assert_eq!(model.reg(Register::Ctrl), pwm.0);
+    }
+
+    #[test]
+    fn read_fault_into_populates_the_out_param() {
+        let (mut dev, model) = Model::driver();
+        let mut fault = Fault(0xFF);
+        dev.read_fault_into(&mut fault).unwrap();
+        assert_eq!(fault, Fault(0x00));
+
+        model.set_reg(Register::Fault, 0x26);
+        dev.read_fault_into(&mut fault).unwrap();
+        assert_eq!(fault, Fault(0x26));
+
+        model.fail_in(1);
+        assert!(dev.read_fault_into(&mut fault).is_err());
+        assert_eq!(fault, Fault(0x26));
+    }
+
+    #[test]
+    fn set_ilimit_checked_rejects_mismatched_limits() {
+        let (mut dev, model) = Model::driver();
+        let limit = |pos, neg| {
+            let mut l = Ilimit(0);
+            l.set_pos_limit(pos);
+            l.set_neg_limit(neg);
+            l
+        };
+        assert!(matches!(
+            dev.set_ilimit_checked(limit(PosLimit::MA2000, NegLimit::MA550)),
+            Err(Error::AsymmetricIlimit)
+        ));
+        assert!(matches!(
+            dev.set_ilimit_checked(limit(PosLimit::MA500, NegLimit::MA2200)),
+            Err(Error::AsymmetricIlimit)
+        ));
+        assert!(model.ops().is_empty());
+
+        let ok = limit(PosLimit::MA1000, NegLimit::MA550);
+        dev.set_ilimit_checked(ok).unwrap();
+        assert_eq!(model.reg(Register::Ilimit), ok.0);
+    }
+
+    #[test]
+    fn write_bank_rgb8_expands_to_10_bits() {
+        let (mut dev, model) = Model::driver();
+        dev.write_bank_rgb8(Bank::B0, [255, 0, 128]).unwrap();
+        assert!(model.writes().into_iter().eq([[
+            Bank::B0 as u8,
+            0xFF,
+            0x03,
+            0x00,
+            0x00,
+            0x02,
+            0x02
+        ]
+        .to_vec()]));
+        assert_eq!(
+            dev.read_bank(Bank::B0).unwrap(),
+            Color::new(0x3FF, 0x000, 0x202)
+        );
+    }
+
+    #[test]
+    fn active_channel_count_counts_nonzero_codes() {
+        let (mut dev, _) = Model::driver();
+        dev.write_bank_color(Bank::B1, Color::new(0, 500, 100))
+            .unwrap();
+        assert_eq!(dev.active_channel_count(Bank::B1).unwrap(), 2);
+        assert_eq!(dev.active_channel_count(Bank::B0).unwrap(), 0);
+        dev.write_bank_color(Bank::B2, Color::new(1, 1, MAX_CODE))
+            .unwrap();
+        assert_eq!(dev.active_channel_count(Bank::B2).unwrap(), 3);
+    }
+
+    #[test]
+    fn bank_currents_round_trip_through_the_registers() {
+        let (mut dev, model) = Model::driver();
+        assert_eq!(BankCurrents::default(), BankCurrents::new(0, 0, 0));
+        for (i, &bank) in Bank::ALL.iter().enumerate() {
+            let c = BankCurrents {
+                r: 0x101 * i as u16,
+                g: MAX_CODE - i as u16,
+                b: 0x0F0,
+            };
+            dev.write_bank_currents(bank, c).unwrap();
+            assert_eq!(dev.read_bank(bank).unwrap(), c);
+        }
+
+        #[allow(deprecated)]
+        dev.write_bank(Bank::B1, 0x001, 0x002, 0x003).unwrap();
+        assert_eq!(model.reg(Register::Ig1Lsb), 0x02);
+        assert_eq!(model.reg(Register::Ib1Lsb), 0x03);
+    }
+
+    #[test]
+    fn ctrl_bits_touch_only_the_mask() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Ctrl, 0x41);
+        dev.set_ctrl_bits(0x02).unwrap();
+        assert_eq!(model.reg(Register::Ctrl), 0x43);
+        dev.set_ctrl_bits(0x02).unwrap();
+        assert_eq!(model.reg(Register::Ctrl), 0x43);
+        dev.clear_ctrl_bits(0x01).unwrap();
+        assert_eq!(model.reg(Register::Ctrl), 0x42);
+        dev.clear_ctrl_bits(0x80).unwrap();
+        assert_eq!(model.reg(Register::Ctrl), 0x42);
+    }
+
+    #[test]
+    fn flush_succeeds_without_bus_traffic() {
+        let (mut dev, model) = Model::driver();
+        dev.write(Register::Fader, 0x20).unwrap();
+        model.clear_ops();
+        dev.flush().unwrap();
+        assert!(model.ops().is_empty());
+        assert_eq!(model.reg(Register::Fader), 0x20);
+    }
+
+    #[test]
+    fn fault_pin_follows_the_mask() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Fault, 0x02);
+        model.set_reg(Register::FaultMask, 0x1D);
+        assert!(!dev.fault_pin_asserted().unwrap());
+        model.set_reg(Register::FaultMask, 0x02);
+        assert!(dev.fault_pin_asserted().unwrap());
+        model.set_reg(Register::Fault, 0x00);
+        assert!(!dev.fault_pin_asserted().unwrap());
+    }
+
+    /// Counts the delays asked for
+    struct Delays(u32);
+
+    impl DelayMs<u8> for Delays {
+        fn delay_ms(&mut self, ms: u8) {
+            assert_eq!(ms, POWER_ON_DELAY_MS);
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    fn power_on_probes_until_the_device_answers() {
+        let model = Model::new();
+        model.fail_in(0);
+        let mut delay = Delays(0);
+        let dev = LM3549::new_after_power_on(model.clone(), &mut delay);
+        assert!(dev.is_ok());
+        assert_eq!(delay.0, 2);
+    }
+
+    #[test]
+    fn power_on_gives_up_after_the_last_probe() {
+        let model = Model::new();
+        for n in 0..POWER_ON_PROBES as usize {
+            model.fail_in(n);
+        }
+        let mut delay = Delays(0);
+        match LM3549::new_after_power_on(model.clone(), &mut delay) {
+            Err((_, Error::I2c(_, op))) => assert_eq!(op.register, Some(Register::Ctrl)),
+            _ => panic!("expected the last probe error"),
+        }
+        assert_eq!(delay.0, POWER_ON_PROBES as u32);
+    }
+
+    #[test]
+    fn with_bank_reselects_the_previous_bank() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::BankSel, Bank::B2.sel());
+        let seen = dev.with_bank(Bank::B1, |dev| dev.selected_bank()).unwrap();
+        assert_eq!(seen, Some(Bank::B1));
+        assert_eq!(model.reg(Register::BankSel), Bank::B2.sel());
+
+        let res: Result<(), _> = dev.with_bank(Bank::B0, |_| Err(Error::InvalidRange));
+        assert!(matches!(res, Err(Error::InvalidRange)));
+        assert_eq!(model.reg(Register::BankSel), Bank::B2.sel());
+    }
+
+    #[test]
+    fn rgbw_saturates_at_max_code() {
+        let (mut dev, _) = Model::driver();
+        dev.set_rgbw(Bank::B1, 0x100, 0x3F0, MAX_CODE, 0x020)
+            .unwrap();
+        assert_eq!(
+            dev.read_bank(Bank::B1).unwrap(),
+            BankCurrents::new(0x120, MAX_CODE, MAX_CODE)
+        );
+        dev.set_rgbw(Bank::B1, 1, 2, 3, u16::MAX).unwrap();
+        assert_eq!(
+            dev.read_bank(Bank::B1).unwrap(),
+            BankCurrents::new(MAX_CODE, MAX_CODE, MAX_CODE)
+        );
+    }
+
+    #[test]
+    fn snapshot_decodes_the_register_file() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::BankSel, 2);
+        model.set_reg(Register::Ig1Lsb, 0x34);
+        model.set_reg(Register::Ig1Msb, 0x02);
+        model.set_reg(Register::Fader, 0x80);
+        model.set_reg(Register::Ctrl, 0x03);
+        model.set_reg(Register::Ilimit, 0x11);
+        model.set_reg(Register::FaultMask, 0x1F);
+        model.set_reg(Register::Fault, 0x04);
+        model.set_reg(Register::User1, 0xA5);
+        model.set_reg(Register::User2, 0x5A);
+        model.set_reg(Register::EepromCtrl, 0x01);
+
+        let s = dev.snapshot().unwrap();
+        assert_eq!(s.bank_sel, 2);
+        assert_eq!(s.banks[0], Color { r: 0, g: 0, b: 0 });
+        assert_eq!(
+            s.banks[1],
+            Color {
+                r: 0,
+                g: 0x234,
+                b: 0
+            }
+        );
+        assert_eq!(s.fader, 0x80);
+        assert_eq!(s.ctrl.0, 0x03);
+        assert_eq!(s.ilimit.0, 0x11);
+        assert_eq!(s.fault_mask, FaultMask::all());
+        assert!(s.fault.uvlo());
+        assert_eq!((s.user1, s.user2), (0xA5, 0x5A));
+        assert_eq!(s.eeprom_ctrl.0, 0x01);
+    }
+
+    #[test]
+    fn read_bank_leaves_the_selection() {
+        let (mut dev, model) = Model::driver();
+        dev.select_bank(Bank::B0).unwrap();
+        model.set_reg(Register::Ir2Lsb, 0x42);
+        model.clear_ops();
+        assert_eq!(
+            dev.read_bank(Bank::B2).unwrap(),
+            Color {
+                r: 0x42,
+                g: 0,
+                b: 0
+            }
+        );
+        assert_eq!(model.reg(Register::BankSel), Bank::B0.sel());
+        assert!(model
+            .writes()
+            .iter()
+            .all(|w| w.len() == 1 && w[0] == Register::Ir2Lsb as u8));
+    }
+
+    #[test]
+    fn uvlo_hook_fires_once_per_transition() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static ENTERED: AtomicUsize = AtomicUsize::new(0);
+        static RECOVERED: AtomicUsize = AtomicUsize::new(0);
+
+        fn hook(event: UvloEvent) {
+            match event {
+                UvloEvent::Entered => ENTERED.fetch_add(1, Ordering::SeqCst),
+                UvloEvent::Recovered => RECOVERED.fetch_add(1, Ordering::SeqCst),
+            };
+        }
+
+        let (mut dev, model) = Model::driver();
+        dev.on_uvlo(hook);
+        dev.get_fault().unwrap();
+        model.set_reg(Register::Fault, 0x04);
+        dev.get_fault().unwrap();
+        dev.get_fault().unwrap();
+        let mut fault = Fault(0);
+        dev.read_fault_into(&mut fault).unwrap();
+        assert_eq!(ENTERED.load(Ordering::SeqCst), 1);
+        assert_eq!(RECOVERED.load(Ordering::SeqCst), 0);
+
+        model.set_reg(Register::Fault, 0x00);
+        dev.get_fault().unwrap();
+        dev.get_fault().unwrap();
+        assert_eq!(RECOVERED.load(Ordering::SeqCst), 1);
+
+        dev.clear_uvlo_hook();
+        model.set_reg(Register::Fault, 0x04);
+        dev.get_fault().unwrap();
+        assert_eq!(ENTERED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn write_block_sends_nothing_for_read_only_or_empty() {
+        let (mut dev, model) = Model::driver();
+        assert!(matches!(
+            dev.write_block(Register::Fault, &[0x01]),
+            Err(Error::BlockTooLong)
+        ));
+        assert!(matches!(
+            dev.write_block(Register::Fault, &[]),
+            Err(Error::BlockTooLong)
+        ));
+        dev.write_block(Register::Ctrl, &[]).unwrap();
+        dev.write_block(Register::EepromCtrl, &[]).unwrap();
+        assert!(model.ops().is_empty());
+    }
+}
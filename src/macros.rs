@@ -0,0 +1,92 @@
+/// Define bank presets as const data with a generated `apply_presets` function
+///
+/// Each bank takes raw codes `(r, g, b)`, currents `(r mA, g mA, b mA)`, 8-bit levels
+/// `rgb8(r, g, b)` or `off`. Out of range values and banks listed twice fail the build.
+///
+/// ```
+/// lm3549::lm3549_banks! {
+///     B0 => (300 mA, 250 mA, 180 mA),
+///     B1 => rgb8(255, 120, 0),
+///     B2 => off,
+/// }
+///
+/// assert_eq!(LM3549_PRESETS[2].1, lm3549::Color::default());
+/// ```
+#[macro_export]
+macro_rules! lm3549_banks {
+    (@color off) => {
+        $crate::Color::new(0, 0, 0)
+    };
+    (@color rgb8($r:expr, $g:expr, $b:expr $(,)?)) => {
+        $crate::Color::from_rgb8($r, $g, $b)
+    };
+    (@color ($r:literal mA, $g:literal mA, $b:literal mA $(,)?)) => {
+        $crate::Color::from_ma($r, $g, $b)
+    };
+    (@color ($r:expr, $g:expr, $b:expr $(,)?)) => {
+        $crate::Color::new($r, $g, $b)
+    };
+    (@parse [$($out:tt)*]) => {
+        $crate::lm3549_banks!(@emit $($out)*);
+    };
+    (@parse [$($out:tt)*] $bank:ident => rgb8 $args:tt $(, $($rest:tt)*)?) => {
+        $crate::lm3549_banks!(@parse [$($out)* ($bank, rgb8 $args)] $($($rest)*)?);
+    };
+    (@parse [$($out:tt)*] $bank:ident => $color:tt $(, $($rest:tt)*)?) => {
+        $crate::lm3549_banks!(@parse [$($out)* ($bank, $color)] $($($rest)*)?);
+    };
+    (@emit $(($bank:ident, $($color:tt)*))*) => {
+        const _: () = assert!(
+            $crate::__private::distinct_banks(&[$($crate::Bank::$bank),*]),
+            "bank listed twice"
+        );
+
+        /// Bank presets
+        const LM3549_PRESETS: &[($crate::Bank, $crate::Color)] = &[
+            $(($crate::Bank::$bank, $crate::lm3549_banks!(@color $($color)*)),)*
+        ];
+
+        /// Write the bank presets
+        #[allow(dead_code)]
+        fn apply_presets<I2C, E>(dev: &mut $crate::LM3549<I2C>) -> Result<(), $crate::Error<E>>
+        where
+            I2C: $crate::__private::i2c::Write<Error = E> + $crate::__private::i2c::Read<Error = E>,
+        {
+            for &(bank, color) in LM3549_PRESETS {
+                dev.write_bank_color(bank, color)?;
+            }
+            Ok(())
+        }
+    };
+    ($($input:tt)*) => {
+        $crate::lm3549_banks!(@parse [] $($input)*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::Model;
+    use crate::{Bank, Color};
+
+    lm3549_banks! {
+        B0 => (0x100, 0x080, 0x040),
+        B1 => rgb8(255, 0, 0),
+        B2 => off,
+    }
+
+    #[test]
+    fn apply_presets_writes_every_bank() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(crate::Register::Ir2Lsb, 0xFF);
+        apply_presets(&mut dev).unwrap();
+        assert_eq!(
+            dev.read_bank(Bank::B0).unwrap(),
+            Color::new(0x100, 0x080, 0x040)
+        );
+        assert_eq!(
+            dev.read_bank(Bank::B1).unwrap(),
+            Color::from_rgb8(255, 0, 0)
+        );
+        assert_eq!(dev.read_bank(Bank::B2).unwrap(), Color::default());
+    }
+}
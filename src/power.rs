@@ -0,0 +1,275 @@
+//! Power supply estimates for the buck-boost converter
+
+use crate::{code_to_ma, ma_to_code, Color, Ilimit, NegLimit, PosLimit};
+
+/// Electrical characteristics of the LED on one channel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LedSpec {
+    /// Forward voltage at the operating current in millivolts
+    pub vf_mv: u16,
+    /// Maximum continuous current in milliamps
+    pub max_ma: u16,
+}
+
+impl LedSpec {
+    /// Largest current code within the LED's maximum current
+    pub fn max_code(&self) -> u16 {
+        ma_to_code(self.max_ma)
+    }
+}
+
+impl Default for LedSpec {
+    /// Conservative high power LED, 3.6 V forward voltage and 350 mA maximum
+    fn default() -> Self {
+        LedSpec {
+            vf_mv: 3600,
+            max_ma: 350,
+        }
+    }
+}
+
+/// Estimate the converter input current in milliamps for a bank driving `specs`
+///
+/// Sums [`estimate_input_current_ma`] over the channels, assuming they run at once.
+pub fn estimate_bank_input_current_ma(
+    vin_mv: u16,
+    bank: &Color,
+    specs: &[LedSpec; 3],
+    efficiency_percent: u8,
+) -> u32 {
+    let codes = [bank.r, bank.g, bank.b];
+    codes
+        .iter()
+        .zip(specs.iter())
+        .map(|(&code, spec)| {
+            let iled_ma = code_to_ma(code) as u32;
+            estimate_input_current_ma(vin_mv, spec.vf_mv, iled_ma, efficiency_percent)
+        })
+        .fold(0, u32::saturating_add)
+}
+
+/// Estimate the converter input current in milliamps
+///
+/// Computed as `vled * iled / (vin * efficiency)`, with the efficiency capped at 100 %.
+/// Saturates at `u32::MAX` for a zero input voltage or efficiency.
+pub fn estimate_input_current_ma(
+    vin_mv: u16,
+    vled_mv: u16,
+    iled_ma: u32,
+    efficiency_percent: u8,
+) -> u32 {
+    let efficiency = efficiency_percent.min(100) as u64;
+    if vin_mv == 0 || efficiency == 0 {
+        return u32::MAX;
+    }
+    let iin = vled_mv as u64 * iled_ma as u64 * 100 / (vin_mv as u64 * efficiency);
+    iin.min(u32::MAX as u64) as u32
+}
+
+/// Headroom added to the average inductor current for ripple, in percent
+const RIPPLE_MARGIN_PERCENT: u32 = 30;
+
+/// Suggest the smallest current limit setting for a load
+///
+/// The average inductor current is the LED current `led_ma` in buck operation
+/// (`vin_mv >= vout_mv`) and the input current `vout * led_ma / (vin * efficiency)` in boost
+/// operation. The positive limit must cover that current plus a 30 % ripple margin; the
+/// negative limit of the same step is paired with it. Returns `None` if no setting fits or
+/// an input is zero.
+pub fn suggest_ilimit(
+    vin_mv: u16,
+    vout_mv: u16,
+    led_ma: u16,
+    efficiency_percent: u8,
+) -> Option<Ilimit> {
+    if vin_mv == 0 || efficiency_percent == 0 {
+        return None;
+    }
+    let iin_ma = estimate_input_current_ma(vin_mv, vout_mv, led_ma as u32, efficiency_percent);
+    let inductor_ma = iin_ma.max(led_ma as u32);
+    let peak_ma = inductor_ma.saturating_mul(100 + RIPPLE_MARGIN_PERCENT) / 100;
+    if peak_ma > u16::MAX as u32 {
+        return None;
+    }
+    let pos = PosLimit::from_milliamps(peak_ma as u16)?;
+    let mut limit = Ilimit(0);
+    limit.set_pos_limit(pos);
+    limit.set_neg_limit(NegLimit::from(pos as u8));
+    Some(limit)
+}
+
+/// Smallest positive current limit covering a channel running at `max_code`
+///
+/// Like [`suggest_ilimit`] in buck operation, the LED current plus the 30 % ripple margin
+/// must fit. Use [`suggest_ilimit`] when the supply can drop below the LED voltage.
+pub fn recommended_pos_limit(max_code: u16) -> PosLimit {
+    let peak_ma = code_to_ma(max_code) as u32 * (100 + RIPPLE_MARGIN_PERCENT) / 100;
+    PosLimit::from_milliamps(peak_ma as u16).unwrap_or(PosLimit::MA2000)
+}
+
+/// Dead band around `Vin = Vled` in which the converter runs buck-boost, in percent
+const DEAD_BAND_PERCENT: u32 = 5;
+
+/// Buck-boost converter operating mode
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConverterMode {
+    /// Input well above the LED voltage
+    Buck,
+    /// Input close to the LED voltage
+    BuckBoost,
+    /// Input well below the LED voltage
+    Boost,
+    /// No LED voltage, the converter does not switch
+    Off,
+}
+
+/// Estimated converter operating point
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OperatingPoint {
+    /// Operating mode
+    pub mode: ConverterMode,
+    /// Switch duty cycle in permille
+    pub duty_permille: u16,
+}
+
+/// Estimate the converter operating point for an input and LED stack voltage
+///
+/// Within ±5 % of `Vin = Vled` the converter is taken to run buck-boost with duty
+/// `Vled / (Vin + Vled)`. Above that it bucks with duty `Vled / Vin`, below it boosts with
+/// duty `1 - Vin / Vled`. Losses are ignored. Without an LED voltage the converter is
+/// [`ConverterMode::Off`] with zero duty.
+pub fn converter_operating_point(vin_mv: u16, vled_mv: u16) -> OperatingPoint {
+    let (vin, vled) = (vin_mv as u32, vled_mv as u32);
+    let (mode, duty) = if vled == 0 {
+        (ConverterMode::Off, 0)
+    } else if vin == 0 {
+        (ConverterMode::Boost, 1000)
+    } else if vin * 100 >= vled * (100 + DEAD_BAND_PERCENT) {
+        (ConverterMode::Buck, vled * 1000 / vin)
+    } else if vin * 100 <= vled * (100 - DEAD_BAND_PERCENT) {
+        (ConverterMode::Boost, (vled - vin) * 1000 / vled)
+    } else {
+        (ConverterMode::BuckBoost, vled * 1000 / (vin + vled))
+    };
+    OperatingPoint {
+        mode,
+        duty_permille: duty as u16,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CURRENT_LSB_UA, MAX_CODE};
+
+    #[test]
+    fn operating_point_modes() {
+        let point = |vin, vled| {
+            let p = converter_operating_point(vin, vled);
+            (p.mode, p.duty_permille)
+        };
+        assert_eq!(point(5000, 2500), (ConverterMode::Buck, 500));
+        assert_eq!(point(3000, 3000), (ConverterMode::BuckBoost, 500));
+        assert_eq!(point(2000, 4000), (ConverterMode::Boost, 500));
+        assert_eq!(point(0, 3000), (ConverterMode::Boost, 1000));
+    }
+
+    #[test]
+    fn operating_point_without_led_voltage() {
+        for vin in [0, 3300, u16::MAX] {
+            let p = converter_operating_point(vin, 0);
+            assert_eq!(p.mode, ConverterMode::Off);
+            assert_eq!(p.duty_permille, 0);
+        }
+    }
+
+    #[test]
+    fn recommended_pos_limit_steps() {
+        assert_eq!(recommended_pos_limit(0).milliamps(), 500);
+        assert_eq!(recommended_pos_limit(563).milliamps(), 500);
+        assert_eq!(recommended_pos_limit(564).milliamps(), 1000);
+        assert_eq!(recommended_pos_limit(MAX_CODE).milliamps(), 1000);
+        assert_eq!(recommended_pos_limit(u16::MAX).milliamps(), 2000);
+    }
+
+    #[test]
+    fn input_current_worked_example() {
+        // 1.26 W of LED power drawn from 3.3 V at 90 % efficiency
+        assert_eq!(estimate_input_current_ma(3300, 3600, 350, 90), 424);
+        assert_eq!(estimate_input_current_ma(3300, 3600, 350, 100), 381);
+        assert_eq!(estimate_input_current_ma(3300, 3600, 350, 150), 381);
+        assert_eq!(estimate_input_current_ma(3300, 3600, 0, 90), 0);
+        assert_eq!(estimate_input_current_ma(0, 3600, 350, 90), u32::MAX);
+        assert_eq!(estimate_input_current_ma(3300, 3600, 350, 0), u32::MAX);
+    }
+
+    #[test]
+    fn bank_input_current_sums_channels() {
+        let specs = [LedSpec::default(); 3];
+        let bank = Color::new(512, 0, 256);
+        assert_eq!(code_to_ma(512), 350);
+        assert_eq!(code_to_ma(256), 175);
+        assert_eq!(
+            estimate_bank_input_current_ma(3300, &bank, &specs, 90),
+            424 + 212
+        );
+        assert_eq!(
+            estimate_bank_input_current_ma(0, &bank, &specs, 90),
+            u32::MAX
+        );
+    }
+
+    /// Positive and negative limit of a suggestion in milliamps
+    fn suggested(vin_mv: u16, vout_mv: u16, led_ma: u16, efficiency: u8) -> Option<(u16, u16)> {
+        let limit = suggest_ilimit(vin_mv, vout_mv, led_ma, efficiency)?;
+        Some((limit.pos_limit().milliamps(), limit.neg_limit().milliamps()))
+    }
+
+    #[test]
+    fn suggest_ilimit_buck_uses_led_current() {
+        // 350 mA plus ripple margin is 455 mA
+        assert_eq!(suggested(5000, 3600, 350, 90), Some((500, 550)));
+        assert_eq!(suggested(5000, 3600, 500, 90), Some((1000, 1100)));
+    }
+
+    #[test]
+    fn suggest_ilimit_boost_from_low_input() {
+        // 1125 mA input current plus ripple margin is 1462 mA
+        assert_eq!(suggested(2000, 3600, 500, 80), Some((1500, 1650)));
+    }
+
+    #[test]
+    fn suggest_ilimit_caps_efficiency() {
+        assert_eq!(suggested(3300, 3600, 700, 90), Some((1500, 1650)));
+        assert_eq!(suggested(3300, 3600, 700, 100), Some((1000, 1100)));
+        assert_eq!(suggested(3300, 3600, 700, 150), Some((1000, 1100)));
+    }
+
+    #[test]
+    fn suggest_ilimit_none_cases() {
+        assert_eq!(suggested(0, 3600, 350, 90), None);
+        assert_eq!(suggested(3300, 3600, 350, 0), None);
+        assert_eq!(suggested(5000, 3600, 2000, 90), None);
+        assert_eq!(suggested(1000, 3600, 700, 90), None);
+    }
+
+    #[test]
+    fn led_max_code() {
+        let spec = LedSpec::default();
+        assert_eq!(spec.max_code(), 511);
+        let ua = spec.max_code() as u32 * CURRENT_LSB_UA;
+        assert!(ua <= 350_000 && ua + CURRENT_LSB_UA > 350_000);
+
+        let full = |max_ma| {
+            LedSpec {
+                vf_mv: 3000,
+                max_ma,
+            }
+            .max_code()
+        };
+        assert_eq!(full(0), 0);
+        assert_eq!(full(700), MAX_CODE);
+        assert_eq!(full(1500), MAX_CODE);
+        assert_eq!(full(u16::MAX), MAX_CODE);
+    }
+}
@@ -1,6 +1,10 @@
-use bitfield::bitfield;
+use core::convert::TryFrom;
+use core::fmt;
 
 /// LM3549 Registers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 pub enum Register {
     BankSel = 0x00,
     Ir0Lsb = 0x01,
@@ -31,8 +35,158 @@ pub enum Register {
     EepromCtrl = 0x40,
 }
 
+impl Register {
+    /// Every register in address order
+    pub const ALL: [Register; 27] = [
+        Register::BankSel,
+        Register::Ir0Lsb,
+        Register::Ir0Msb,
+        Register::Ig0Lsb,
+        Register::Ig0Msb,
+        Register::Ib0Lsb,
+        Register::Ib0Msb,
+        Register::Ir1Lsb,
+        Register::Ir1Msb,
+        Register::Ig1Lsb,
+        Register::Ig1Msb,
+        Register::Ib1Lsb,
+        Register::Ib1Msb,
+        Register::Ir2Lsb,
+        Register::Ir2Msb,
+        Register::Ig2Lsb,
+        Register::Ig2Msb,
+        Register::Ib2Lsb,
+        Register::Ib2Msb,
+        Register::Fader,
+        Register::Ctrl,
+        Register::Ilimit,
+        Register::FaultMask,
+        Register::Fault,
+        Register::User1,
+        Register::User2,
+        Register::EepromCtrl,
+    ];
+
+    /// Every register in address order, skipping the reserved address 0x18
+    pub fn iter() -> impl Iterator<Item = Register> {
+        Register::ALL.iter().copied()
+    }
+
+    /// Name of the register as in the datasheet
+    #[cfg(feature = "names")]
+    pub fn name(&self) -> &'static str {
+        use Register::*;
+        match self {
+            BankSel => "BankSel",
+            Ir0Lsb => "Ir0Lsb",
+            Ir0Msb => "Ir0Msb",
+            Ig0Lsb => "Ig0Lsb",
+            Ig0Msb => "Ig0Msb",
+            Ib0Lsb => "Ib0Lsb",
+            Ib0Msb => "Ib0Msb",
+            Ir1Lsb => "Ir1Lsb",
+            Ir1Msb => "Ir1Msb",
+            Ig1Lsb => "Ig1Lsb",
+            Ig1Msb => "Ig1Msb",
+            Ib1Lsb => "Ib1Lsb",
+            Ib1Msb => "Ib1Msb",
+            Ir2Lsb => "Ir2Lsb",
+            Ir2Msb => "Ir2Msb",
+            Ig2Lsb => "Ig2Lsb",
+            Ig2Msb => "Ig2Msb",
+            Ib2Lsb => "Ib2Lsb",
+            Ib2Msb => "Ib2Msb",
+            Fader => "Fader",
+            Ctrl => "Ctrl",
+            Ilimit => "Ilimit",
+            FaultMask => "FaultMask",
+            Fault => "Fault",
+            User1 => "User1",
+            User2 => "User2",
+            EepromCtrl => "EepromCtrl",
+        }
+    }
+}
+
+/// The register name with the `names` feature, otherwise its address in hex
+impl fmt::Display for Register {
+    #[cfg(feature = "names")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+
+    #[cfg(not(feature = "names"))]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#04x}", *self as u8)
+    }
+}
+
+/// Addresses of the contiguous register block, BankSel to Fault
+pub const REGISTER_SPAN: core::ops::RangeInclusive<u8> = 0x00..=0x17;
+
+/// Address of the EEPROM control register, outside [`REGISTER_SPAN`]
+pub const EEPROM_CTRL_ADDR: u8 = 0x40;
+
+const _: () = {
+    assert!(*REGISTER_SPAN.start() == Register::BankSel as u8);
+    assert!(*REGISTER_SPAN.end() == Register::Fault as u8);
+    assert!(EEPROM_CTRL_ADDR == Register::EepromCtrl as u8);
+};
+
+// The compiler already rejects duplicate discriminants; also check that the register list
+// is strictly increasing, so no register is listed twice, and stays within the address map.
+const _: () = {
+    let mut i = 1;
+    while i < Register::ALL.len() {
+        assert!((Register::ALL[i - 1] as u8) < (Register::ALL[i] as u8));
+        assert!(Register::ALL[i] as u8 != 0x18);
+        i += 1;
+    }
+    assert!(Register::ALL[Register::ALL.len() - 1] as u8 <= 0x40);
+};
+
+impl TryFrom<u8> for Register {
+    type Error = u8;
+
+    /// Register at an address, the address itself if there is none
+    fn try_from(x: u8) -> Result<Self, u8> {
+        use Register::*;
+        Ok(match x {
+            0x00 => BankSel,
+            0x01 => Ir0Lsb,
+            0x02 => Ir0Msb,
+            0x03 => Ig0Lsb,
+            0x04 => Ig0Msb,
+            0x05 => Ib0Lsb,
+            0x06 => Ib0Msb,
+            0x07 => Ir1Lsb,
+            0x08 => Ir1Msb,
+            0x09 => Ig1Lsb,
+            0x0A => Ig1Msb,
+            0x0B => Ib1Lsb,
+            0x0C => Ib1Msb,
+            0x0D => Ir2Lsb,
+            0x0E => Ir2Msb,
+            0x0F => Ig2Lsb,
+            0x10 => Ig2Msb,
+            0x11 => Ib2Lsb,
+            0x12 => Ib2Msb,
+            0x13 => Fader,
+            0x14 => Ctrl,
+            0x15 => Ilimit,
+            0x16 => FaultMask,
+            0x17 => Fault,
+            0x19 => User1,
+            0x1A => User2,
+            0x40 => EepromCtrl,
+            _ => return Err(x),
+        })
+    }
+}
+
 /// Selects bank of current settings
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 pub enum Bank {
     /// Bank 0
     B0 = 0x01,
@@ -42,6 +196,78 @@ pub enum Bank {
     B2 = 0x0D,
 }
 
+impl Bank {
+    /// All banks in order
+    pub const ALL: [Bank; 3] = [Bank::B0, Bank::B1, Bank::B2];
+
+    /// All banks in order
+    pub fn iter() -> impl Iterator<Item = Bank> {
+        Bank::ALL.iter().copied()
+    }
+
+    /// Code selecting this bank in the bank select register
+    pub fn sel(self) -> u8 {
+        match self {
+            Bank::B0 => 0,
+            Bank::B1 => 1,
+            Bank::B2 => 2,
+        }
+    }
+
+    /// Bank selected by a bank select register code
+    pub fn from_sel(code: u8) -> Option<Bank> {
+        match code {
+            0 => Some(Bank::B0),
+            1 => Some(Bank::B1),
+            2 => Some(Bank::B2),
+            _ => None,
+        }
+    }
+}
+
+/// LED driver channel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Channel {
+    /// Red driver
+    Red,
+    /// Green driver
+    Green,
+    /// Blue driver
+    Blue,
+}
+
+impl Channel {
+    /// All channels in register order
+    pub fn all() -> [Channel; 3] {
+        [Channel::Red, Channel::Green, Channel::Blue]
+    }
+}
+
+/// Current register of a channel in a bank, the LSB or the MSB half
+pub fn bank_channel_reg(bank: Bank, channel: Channel, msb: bool) -> Register {
+    use Register::*;
+    match (bank, channel, msb) {
+        (Bank::B0, Channel::Red, false) => Ir0Lsb,
+        (Bank::B0, Channel::Red, true) => Ir0Msb,
+        (Bank::B0, Channel::Green, false) => Ig0Lsb,
+        (Bank::B0, Channel::Green, true) => Ig0Msb,
+        (Bank::B0, Channel::Blue, false) => Ib0Lsb,
+        (Bank::B0, Channel::Blue, true) => Ib0Msb,
+        (Bank::B1, Channel::Red, false) => Ir1Lsb,
+        (Bank::B1, Channel::Red, true) => Ir1Msb,
+        (Bank::B1, Channel::Green, false) => Ig1Lsb,
+        (Bank::B1, Channel::Green, true) => Ig1Msb,
+        (Bank::B1, Channel::Blue, false) => Ib1Lsb,
+        (Bank::B1, Channel::Blue, true) => Ib1Msb,
+        (Bank::B2, Channel::Red, false) => Ir2Lsb,
+        (Bank::B2, Channel::Red, true) => Ir2Msb,
+        (Bank::B2, Channel::Green, false) => Ig2Lsb,
+        (Bank::B2, Channel::Green, true) => Ig2Msb,
+        (Bank::B2, Channel::Blue, false) => Ib2Lsb,
+        (Bank::B2, Channel::Blue, true) => Ib2Msb,
+    }
+}
+
 /// Buck-boost converter positive current limit
 #[derive(Copy, Clone, Debug)]
 pub enum PosLimit {
@@ -55,6 +281,37 @@ pub enum PosLimit {
     MA2000 = 3,
 }
 
+impl PosLimit {
+    /// Highest limit in milliamps
+    pub const MAX_MA: u16 = 2000;
+
+    /// Lowest limit in milliamps
+    pub const MIN_MA: u16 = 500;
+
+    /// Limits in ascending order
+    const ALL: [PosLimit; 4] = [
+        PosLimit::MA500,
+        PosLimit::MA1000,
+        PosLimit::MA1500,
+        PosLimit::MA2000,
+    ];
+
+    /// Limit in milliamps
+    pub fn milliamps(self) -> u16 {
+        match self {
+            PosLimit::MA500 => 500,
+            PosLimit::MA1000 => 1000,
+            PosLimit::MA1500 => 1500,
+            PosLimit::MA2000 => 2000,
+        }
+    }
+
+    /// Smallest limit of at least `ma` milliamps, `None` if above the highest limit
+    pub fn from_milliamps(ma: u16) -> Option<Self> {
+        PosLimit::ALL.iter().copied().find(|l| l.milliamps() >= ma)
+    }
+}
+
 impl From<PosLimit> for u8 {
     fn from(p: PosLimit) -> Self {
         p as u8
@@ -77,7 +334,7 @@ impl From<u8> for PosLimit {
 pub enum NegLimit {
     /// 550 mA
     MA550 = 0,
-    /// 1000 mA
+    /// 1100 mA
     MA1100 = 1,
     /// 1650 mA
     MA1650 = 2,
@@ -85,6 +342,37 @@ pub enum NegLimit {
     MA2200 = 3,
 }
 
+impl NegLimit {
+    /// Highest limit in milliamps
+    pub const MAX_MA: u16 = 2200;
+
+    /// Lowest limit in milliamps
+    pub const MIN_MA: u16 = 550;
+
+    /// Limits in ascending order
+    const ALL: [NegLimit; 4] = [
+        NegLimit::MA550,
+        NegLimit::MA1100,
+        NegLimit::MA1650,
+        NegLimit::MA2200,
+    ];
+
+    /// Limit in milliamps
+    pub fn milliamps(self) -> u16 {
+        match self {
+            NegLimit::MA550 => 550,
+            NegLimit::MA1100 => 1100,
+            NegLimit::MA1650 => 1650,
+            NegLimit::MA2200 => 2200,
+        }
+    }
+
+    /// Smallest limit of at least `ma` milliamps, `None` if above the highest limit
+    pub fn from_milliamps(ma: u16) -> Option<Self> {
+        NegLimit::ALL.iter().copied().find(|l| l.milliamps() >= ma)
+    }
+}
+
 impl From<NegLimit> for u8 {
     fn from(n: NegLimit) -> Self {
         n as u8
@@ -102,14 +390,84 @@ impl From<u8> for NegLimit {
     }
 }
 
-bitfield! {
-  /// Current limit register
-  pub struct Ilimit(u8);
-  impl Debug;
-  /// Positive limit
-  pub u8, from into PosLimit, softstart, set_softstart: 5,4;
-  /// Negative limit
-  pub u8, from into NegLimit, timeout, set_timeout: 1, 0;
+/// Current limit register
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Ilimit(pub u8);
+
+impl Ilimit {
+    /// Positive limit
+    pub fn pos_limit(&self) -> PosLimit {
+        PosLimit::from((self.0 >> 4) & 0x03)
+    }
+
+    /// Positive limit
+    pub fn set_pos_limit(&mut self, value: PosLimit) {
+        self.0 = (self.0 & !0x30) | ((u8::from(value) & 0x03) << 4);
+    }
+
+    /// Negative limit
+    pub fn neg_limit(&self) -> NegLimit {
+        NegLimit::from(self.0 & 0x03)
+    }
+
+    /// Negative limit
+    pub fn set_neg_limit(&mut self, value: NegLimit) {
+        self.0 = (self.0 & !0x03) | (u8::from(value) & 0x03);
+    }
+
+    /// Positive limit
+    #[deprecated(note = "use `pos_limit`, the field was misnamed")]
+    pub fn softstart(&self) -> PosLimit {
+        self.pos_limit()
+    }
+
+    /// Positive limit
+    #[deprecated(note = "use `set_pos_limit`, the field was misnamed")]
+    pub fn set_softstart(&mut self, value: PosLimit) {
+        self.set_pos_limit(value)
+    }
+
+    /// Negative limit
+    #[deprecated(note = "use `neg_limit`, the field was misnamed")]
+    pub fn timeout(&self) -> NegLimit {
+        self.neg_limit()
+    }
+
+    /// Negative limit
+    #[deprecated(note = "use `set_neg_limit`, the field was misnamed")]
+    pub fn set_timeout(&mut self, value: NegLimit) {
+        self.set_neg_limit(value)
+    }
+
+    /// Largest ratio between positive and negative limit considered intentional
+    pub const MAX_RATIO: u16 = 2;
+
+    /// Whether one limit is more than [`Ilimit::MAX_RATIO`] times the other
+    pub fn is_asymmetric(&self) -> bool {
+        let pos = self.pos_limit().milliamps();
+        let neg = self.neg_limit().milliamps();
+        pos > neg * Self::MAX_RATIO || neg > pos * Self::MAX_RATIO
+    }
+}
+
+impl fmt::Debug for Ilimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ilimit")
+            .field("pos_limit", &self.pos_limit())
+            .field("neg_limit", &self.neg_limit())
+            .finish()
+    }
+}
+
+impl fmt::Display for Ilimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Ilimit(pos={}mA, neg={}mA)",
+            self.pos_limit().milliamps(),
+            self.neg_limit().milliamps()
+        )
+    }
 }
 
 impl Default for Ilimit {
@@ -121,6 +479,7 @@ impl Default for Ilimit {
 
 /// Source of open/short fault
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 pub enum OpenShort {
     /// No fault
     None = 0,
@@ -143,8 +502,21 @@ impl From<u8> for OpenShort {
     }
 }
 
+impl OpenShort {
+    /// Faulty channel, `None` if there is no fault
+    pub fn channel(self) -> Option<Channel> {
+        match self {
+            OpenShort::None => None,
+            OpenShort::Red => Some(Channel::Red),
+            OpenShort::Green => Some(Channel::Green),
+            OpenShort::Blue => Some(Channel::Blue),
+        }
+    }
+}
+
 /// Selects how long device stays in active mode after all enable pins have gone low.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 pub enum Timeout {
     /// 125 ms
     MS125 = 0,
@@ -175,6 +547,7 @@ impl From<Timeout> for u8 {
 
 /// Selects soft start time
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 pub enum SoftStart {
     /// No soft start
     None = 0,
@@ -197,24 +570,79 @@ impl From<u8> for SoftStart {
     }
 }
 
+impl SoftStart {
+    /// Soft start time in milliseconds
+    pub fn as_millis(self) -> u16 {
+        match self {
+            SoftStart::None => 0,
+            SoftStart::MS500 => 500,
+            SoftStart::MS1000 => 1000,
+            SoftStart::MS2000 => 2000,
+        }
+    }
+}
+
 impl From<SoftStart> for u8 {
     fn from(s: SoftStart) -> Self {
         s as u8
     }
 }
 
-bitfield! {
-  /// Control register
-  pub struct Ctrl(u8);
-  impl Debug;
-  /// Soft start control
-  pub u8, from into SoftStart, softstart, set_softstart: 5,4;
-  /// Timeout control
-  pub u8, from into Timeout, timeout, set_timeout: 3, 2;
-  /// Enable fade control from Fader register
-  pub mfe, set_mfe: 1;
-  /// Enable fade control from PWM input (overrides mfe).
-  pub pwm, set_pwm: 0;
+/// Control register
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Ctrl(pub u8);
+
+impl Ctrl {
+    /// Soft start control
+    pub fn softstart(&self) -> SoftStart {
+        SoftStart::from((self.0 >> 4) & 0x03)
+    }
+
+    /// Soft start control
+    pub fn set_softstart(&mut self, value: SoftStart) {
+        self.0 = (self.0 & !0x30) | ((u8::from(value) & 0x03) << 4);
+    }
+
+    /// Timeout control
+    pub fn timeout(&self) -> Timeout {
+        Timeout::from((self.0 >> 2) & 0x03)
+    }
+
+    /// Timeout control
+    pub fn set_timeout(&mut self, value: Timeout) {
+        self.0 = (self.0 & !0x0C) | ((u8::from(value) & 0x03) << 2);
+    }
+
+    /// Enable fade control from Fader register
+    pub const fn mfe(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    /// Enable fade control from Fader register
+    pub fn set_mfe(&mut self, value: bool) {
+        self.0 = (self.0 & !0x02) | ((value as u8) << 1);
+    }
+
+    /// Enable fade control from PWM input (overrides mfe).
+    pub const fn pwm(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+
+    /// Enable fade control from PWM input (overrides mfe).
+    pub fn set_pwm(&mut self, value: bool) {
+        self.0 = (self.0 & !0x01) | value as u8;
+    }
+}
+
+impl fmt::Debug for Ctrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ctrl")
+            .field("softstart", &self.softstart())
+            .field("timeout", &self.timeout())
+            .field("mfe", &self.mfe())
+            .field("pwm", &self.pwm())
+            .finish()
+    }
 }
 
 impl Default for Ctrl {
@@ -224,20 +652,47 @@ impl Default for Ctrl {
     }
 }
 
-bitfield! {
-  /// Fault register
-  pub struct Fault(u8);
-  impl Debug;
-  /// Shorted drivers
-  pub u8, into OpenShort, short, _: 6,5;
-  /// Open drivers
-  pub u8, into OpenShort, open, _: 4,3;
-  /// Under voltage lock-out
-  pub uvlo, _: 2;
-  /// Temperature shutdown
-  pub tsd, _: 1;
-  /// Overcurrent
-  pub ocp, _: 0;
+/// Fault register
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Fault(pub u8);
+
+impl Fault {
+    /// Shorted drivers
+    pub fn short(&self) -> OpenShort {
+        OpenShort::from((self.0 >> 5) & 0x03)
+    }
+
+    /// Open drivers
+    pub fn open(&self) -> OpenShort {
+        OpenShort::from((self.0 >> 3) & 0x03)
+    }
+
+    /// Under voltage lock-out
+    pub const fn uvlo(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    /// Temperature shutdown
+    pub const fn tsd(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    /// Overcurrent
+    pub const fn ocp(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+}
+
+impl fmt::Debug for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Fault")
+            .field("short", &self.short())
+            .field("open", &self.open())
+            .field("uvlo", &self.uvlo())
+            .field("tsd", &self.tsd())
+            .field("ocp", &self.ocp())
+            .finish()
+    }
 }
 
 impl Fault {
@@ -246,26 +701,149 @@ impl Fault {
         !self.is_err()
     }
 
+    /// Shorted driver reported by the device
+    ///
+    /// The register encodes a single channel, so if several drivers are shorted at once only
+    /// one of them is reported.
+    pub fn primary_short(&self) -> OpenShort {
+        self.short()
+    }
+
+    /// Open driver reported by the device
+    ///
+    /// The register encodes a single channel, so if several drivers are open at once only
+    /// one of them is reported.
+    pub fn primary_open(&self) -> OpenShort {
+        self.open()
+    }
+
     /// One or more fault flags are active
     pub fn is_err(&self) -> bool {
-        self.0 == 0x00
+        self.0 & 0x7F != 0x00
+    }
+
+    /// A kind of fault is active
+    pub fn has(&self, kind: FaultKind) -> bool {
+        match kind {
+            FaultKind::Short => self.0 & 0x60 != 0,
+            FaultKind::Open => self.0 & 0x18 != 0,
+            FaultKind::Uvlo => self.uvlo(),
+            FaultKind::Tsd => self.tsd(),
+            FaultKind::Ocp => self.ocp(),
+        }
+    }
+
+    /// Most severe active fault: TSD, then OCP, UVLO, short and open
+    pub fn highest_priority(&self) -> Option<FaultKind> {
+        FaultKind::BY_PRIORITY
+            .iter()
+            .copied()
+            .find(|&k| self.has(k))
+    }
+
+    /// An active fault is enabled in `mask`, so the FAULT output is asserted
+    pub fn asserts_pin(&self, mask: FaultMask) -> bool {
+        FaultKind::BY_PRIORITY
+            .iter()
+            .any(|&k| self.has(k) && mask.has(k))
+    }
+
+    /// Any of the kinds of fault is active
+    pub fn has_any(&self, kinds: &[FaultKind]) -> bool {
+        kinds.iter().any(|&k| self.has(k))
+    }
+
+    /// All of the kinds of fault are active
+    pub fn has_all(&self, kinds: &[FaultKind]) -> bool {
+        kinds.iter().all(|&k| self.has(k))
     }
 }
 
-bitfield! {
-  /// Enable faults to drive the FAULT open-drain output
-  pub struct FaultMask(u8);
-  impl Debug;
-  /// Shorted drivers
-  pub short, _: 4;
-  /// Open drivers
-  pub open, _: 3;
-  /// Under voltage lock-out
-  pub uvlo, _: 2;
-  /// Temperature shutdown
-  pub tsd, _: 1;
-  /// Overcurrent
-  pub ocp, _: 0;
+/// Kind of fault reported in the fault register
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    /// Shorted driver
+    Short,
+    /// Open driver
+    Open,
+    /// Under voltage lock-out
+    Uvlo,
+    /// Temperature shutdown
+    Tsd,
+    /// Overcurrent
+    Ocp,
+}
+
+impl FaultKind {
+    /// All kinds, most severe first
+    pub const BY_PRIORITY: [FaultKind; 5] = [
+        FaultKind::Tsd,
+        FaultKind::Ocp,
+        FaultKind::Uvlo,
+        FaultKind::Short,
+        FaultKind::Open,
+    ];
+}
+
+/// Enable faults to drive the FAULT open-drain output
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct FaultMask(pub u8);
+
+impl FaultMask {
+    /// Shorted drivers
+    pub const fn short(&self) -> bool {
+        self.0 & 0x10 != 0
+    }
+
+    /// Open drivers
+    pub const fn open(&self) -> bool {
+        self.0 & 0x08 != 0
+    }
+
+    /// Under voltage lock-out
+    pub const fn uvlo(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    /// Temperature shutdown
+    pub const fn tsd(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    /// Overcurrent
+    pub const fn ocp(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+}
+
+impl fmt::Debug for FaultMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FaultMask")
+            .field("short", &self.short())
+            .field("open", &self.open())
+            .field("uvlo", &self.uvlo())
+            .field("tsd", &self.tsd())
+            .field("ocp", &self.ocp())
+            .finish()
+    }
+}
+
+impl FaultMask {
+    /// All faults enabled
+    pub fn all() -> Self {
+        FaultMask(0x1F)
+    }
+
+    /// A kind of fault is enabled
+    pub fn has(&self, kind: FaultKind) -> bool {
+        match kind {
+            FaultKind::Short => self.short(),
+            FaultKind::Open => self.open(),
+            FaultKind::Uvlo => self.uvlo(),
+            FaultKind::Tsd => self.tsd(),
+            FaultKind::Ocp => self.ocp(),
+        }
+    }
 }
 
 impl Default for FaultMask {
@@ -274,3 +852,262 @@ impl Default for FaultMask {
         FaultMask(0x00)
     }
 }
+
+/// EEPROM control register
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct EepromCtrl(pub u8);
+
+impl EepromCtrl {
+    /// EEPROM operation finished
+    pub const fn ready(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    /// Program the configuration registers into EEPROM
+    pub const fn prog(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    /// Program the configuration registers into EEPROM
+    pub fn set_prog(&mut self, value: bool) {
+        self.0 = (self.0 & !0x02) | ((value as u8) << 1);
+    }
+
+    /// Load the configuration registers from EEPROM
+    pub const fn init(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+
+    /// Load the configuration registers from EEPROM
+    pub fn set_init(&mut self, value: bool) {
+        self.0 = (self.0 & !0x01) | value as u8;
+    }
+}
+
+impl fmt::Debug for EepromCtrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EepromCtrl")
+            .field("ready", &self.ready())
+            .field("prog", &self.prog())
+            .field("init", &self.init())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ctrl_bit_layout() {
+        let mut ctrl = Ctrl(0);
+        ctrl.set_softstart(SoftStart::MS2000);
+        assert_eq!(ctrl.0, 0x30);
+        ctrl.set_timeout(Timeout::MS1000);
+        assert_eq!(ctrl.0, 0x3C);
+        ctrl.set_mfe(true);
+        ctrl.set_pwm(true);
+        assert_eq!(ctrl.0, 0x3F);
+        ctrl.set_softstart(SoftStart::None);
+        ctrl.set_mfe(false);
+        assert_eq!(ctrl.0, 0x0D);
+        assert_eq!(Ctrl(0xFF).softstart() as u8, 3);
+        assert_eq!(Ctrl(0x04).timeout() as u8, 1);
+    }
+
+    #[test]
+    fn ilimit_bit_layout() {
+        let mut ilimit = Ilimit(0xCC);
+        ilimit.set_pos_limit(PosLimit::MA1500);
+        ilimit.set_neg_limit(NegLimit::MA2200);
+        assert_eq!(ilimit.0, 0xEF);
+        assert_eq!(ilimit.pos_limit() as u8, 2);
+        assert_eq!(ilimit.neg_limit() as u8, 3);
+    }
+
+    #[test]
+    fn fault_and_eeprom_ctrl_bit_layout() {
+        let fault = Fault(0x60 | 0x08);
+        assert_eq!(fault.short() as u8, 3);
+        assert_eq!(fault.open() as u8, 1);
+        let mut ctrl = EepromCtrl(0x04);
+        ctrl.set_prog(true);
+        ctrl.set_init(true);
+        assert_eq!(ctrl.0, 0x07);
+        ctrl.set_prog(false);
+        assert!(ctrl.ready() && ctrl.init() && !ctrl.prog());
+    }
+
+    #[test]
+    fn bank_channel_reg_all_combinations() {
+        for bank in Bank::iter() {
+            for (i, channel) in Channel::all().iter().enumerate() {
+                let lsb = bank_channel_reg(bank, *channel, false) as u8;
+                let msb = bank_channel_reg(bank, *channel, true) as u8;
+                assert_eq!(lsb, bank as u8 + 2 * i as u8);
+                assert_eq!(msb, lsb + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn channel_all_in_register_order() {
+        assert_eq!(
+            Channel::all(),
+            [Channel::Red, Channel::Green, Channel::Blue]
+        );
+        for (i, &channel) in Channel::all().iter().enumerate() {
+            let lsb = bank_channel_reg(Bank::B0, channel, false);
+            assert_eq!(lsb as u8, Bank::B0 as u8 + 2 * i as u8);
+        }
+    }
+
+    #[test]
+    fn fault_is_err_for_every_flag() {
+        for bit in 0..7 {
+            let fault = Fault(1 << bit);
+            assert!(fault.is_err(), "bit {}", bit);
+            assert!(!fault.is_ok(), "bit {}", bit);
+        }
+    }
+
+    #[test]
+    fn fault_ignores_reserved_bit() {
+        assert!(Fault(0x80).is_ok());
+        assert!(Fault(0x00).is_ok());
+    }
+
+    #[test]
+    fn fault_has_any_and_has_all() {
+        let tsd = Fault(0x02);
+        assert!(tsd.has_any(&[FaultKind::Tsd, FaultKind::Ocp]));
+        assert!(!tsd.has_all(&[FaultKind::Tsd, FaultKind::Ocp]));
+        assert!(tsd.has_all(&[FaultKind::Tsd]));
+        assert!(!tsd.has_any(&[FaultKind::Short, FaultKind::Open]));
+
+        let both = Fault(0x03);
+        assert!(both.has_all(&[FaultKind::Tsd, FaultKind::Ocp]));
+        assert!(!Fault(0x00).has_any(&[FaultKind::Tsd, FaultKind::Ocp]));
+        assert!(!tsd.has_any(&[]));
+        assert!(tsd.has_all(&[]));
+    }
+
+    #[test]
+    fn limit_bounds_match_the_variants() {
+        assert_eq!(PosLimit::MIN_MA, PosLimit::MA500.milliamps());
+        assert_eq!(PosLimit::MAX_MA, PosLimit::MA2000.milliamps());
+        assert_eq!(NegLimit::MIN_MA, NegLimit::MA550.milliamps());
+        assert_eq!(NegLimit::MAX_MA, NegLimit::MA2200.milliamps());
+        assert_eq!(
+            PosLimit::from_milliamps(PosLimit::MAX_MA).map(|l| l as u8),
+            Some(PosLimit::MA2000 as u8)
+        );
+        assert!(PosLimit::from_milliamps(PosLimit::MAX_MA + 1).is_none());
+        assert_eq!(
+            NegLimit::from_milliamps(NegLimit::MAX_MA).map(|l| l as u8),
+            Some(NegLimit::MA2200 as u8)
+        );
+        assert!(NegLimit::from_milliamps(NegLimit::MAX_MA + 1).is_none());
+    }
+
+    #[test]
+    fn primary_short_and_open_decode_each_source() {
+        let sources = [
+            OpenShort::None,
+            OpenShort::Red,
+            OpenShort::Green,
+            OpenShort::Blue,
+        ];
+        for (code, &source) in sources.iter().enumerate() {
+            let code = code as u8;
+            assert_eq!(Fault(code << 5).primary_short() as u8, source as u8);
+            assert_eq!(Fault(code << 5).primary_open() as u8, OpenShort::None as u8);
+            assert_eq!(Fault(code << 3).primary_open() as u8, source as u8);
+            assert_eq!(
+                Fault(code << 3).primary_short() as u8,
+                OpenShort::None as u8
+            );
+        }
+    }
+
+    #[test]
+    fn highest_priority_fault() {
+        // TSD and an open red driver
+        assert_eq!(Fault(0x0A).highest_priority(), Some(FaultKind::Tsd));
+        assert_eq!(Fault(0x0D).highest_priority(), Some(FaultKind::Ocp));
+        assert_eq!(Fault(0x2C).highest_priority(), Some(FaultKind::Uvlo));
+        assert_eq!(Fault(0x28).highest_priority(), Some(FaultKind::Short));
+        assert_eq!(Fault(0x08).highest_priority(), Some(FaultKind::Open));
+        assert_eq!(Fault(0x00).highest_priority(), None);
+    }
+
+    #[test]
+    fn register_span_is_contiguous() {
+        for x in REGISTER_SPAN {
+            assert_eq!(Register::try_from(x).map(|r| r as u8), Ok(x));
+        }
+        assert_eq!(Register::try_from(*REGISTER_SPAN.end() + 1), Err(0x18));
+        assert!(!REGISTER_SPAN.contains(&EEPROM_CTRL_ADDR));
+        assert_eq!(
+            Register::try_from(EEPROM_CTRL_ADDR),
+            Ok(Register::EepromCtrl)
+        );
+    }
+
+    #[test]
+    fn ilimit_display() {
+        extern crate std;
+        use std::string::ToString;
+
+        assert_eq!(
+            Ilimit::default().to_string(),
+            "Ilimit(pos=1000mA, neg=1100mA)"
+        );
+    }
+
+    #[test]
+    fn register_iter_is_in_address_order() {
+        assert!(Register::iter().all(|r| r as u8 != 0x18));
+        for (a, b) in Register::iter().zip(Register::iter().skip(1)) {
+            assert!((a as u8) < (b as u8), "{:?} before {:?}", a, b);
+        }
+        assert_eq!(Register::iter().count(), Register::ALL.len());
+        assert_eq!(Register::iter().next(), Some(Register::BankSel));
+        assert_eq!(Register::iter().last(), Some(Register::EepromCtrl));
+    }
+
+    #[test]
+    fn bank_iter_in_order() {
+        let mut banks = Bank::iter();
+        assert_eq!(banks.next(), Some(Bank::B0));
+        assert_eq!(banks.next(), Some(Bank::B1));
+        assert_eq!(banks.next(), Some(Bank::B2));
+        assert_eq!(banks.next(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "names")]
+    fn register_names() {
+        extern crate std;
+        use std::string::ToString;
+
+        assert_eq!(Register::BankSel.name(), "BankSel");
+        assert_eq!(Register::Ig1Msb.name(), "Ig1Msb");
+        assert_eq!(Register::FaultMask.name(), "FaultMask");
+        assert_eq!(Register::EepromCtrl.name(), "EepromCtrl");
+        for r in Register::iter() {
+            assert_eq!(r.to_string(), r.name());
+            assert_eq!(Register::iter().filter(|o| o.name() == r.name()).count(), 1);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "names"))]
+    fn register_display_without_names() {
+        extern crate std;
+        use std::string::ToString;
+
+        assert_eq!(Register::Ctrl.to_string(), "0x14");
+        assert_eq!(Register::EepromCtrl.to_string(), "0x40");
+    }
+}
@@ -0,0 +1,236 @@
+//! Driver shared between interrupt handlers and the main loop
+
+use core::cell::{Cell, RefCell};
+
+use critical_section::Mutex;
+use hal::blocking::i2c;
+
+use crate::{Bank, Channel, Error, LM3549};
+#[cfg(feature = "effects")]
+use crate::{LedChannel, MAX_CODE};
+
+/// Driver behind a critical-section mutex
+///
+/// Every access runs inside a critical section, so keep it short. Single transactions such
+/// as [`LM3549::set_fader`], [`LM3549::write`] or [`LM3549::write_bank_currents`] are fine to
+/// run from an interrupt handler. Multi-step operations that wait between writes, like
+/// `breathe` or `strobe_with_timer` of the `effects` feature, block interrupts for their
+/// whole duration and must not be run through [`SharedLM3549::with`].
+pub struct SharedLM3549<I2C> {
+    inner: Mutex<RefCell<LM3549<I2C>>>,
+    /// Banks already split into handles, one bit per bank select code
+    split: Mutex<Cell<u8>>,
+}
+
+impl<I2C> SharedLM3549<I2C> {
+    /// Share a driver
+    pub const fn new(dev: LM3549<I2C>) -> Self {
+        SharedLM3549 {
+            inner: Mutex::new(RefCell::new(dev)),
+            split: Mutex::new(Cell::new(0)),
+        }
+    }
+
+    /// Run `f` with exclusive access to the driver inside a critical section
+    ///
+    /// Panics if called again from within `f`.
+    pub fn with<R>(&self, f: impl FnOnce(&mut LM3549<I2C>) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.inner.borrow(cs).borrow_mut()))
+    }
+
+    /// Split into one handle per channel of `bank`, `None` if `bank` was split before
+    ///
+    /// Each handle only touches its own channel, while bank selection, the fader and faults
+    /// stay with the shared driver. A bank is split at most once, so no two handles own the
+    /// same channel.
+    pub fn split(&self, bank: Bank) -> Option<Channels<'_, I2C>> {
+        let bit = 1 << bank.sel();
+        let taken = critical_section::with(|cs| {
+            let split = self.split.borrow(cs);
+            let taken = split.get() & bit != 0;
+            split.set(split.get() | bit);
+            taken
+        });
+        if taken {
+            return None;
+        }
+        let handle = |channel| ChannelHandle {
+            shared: self,
+            bank,
+            channel,
+        };
+        Some(Channels {
+            red: handle(Channel::Red),
+            green: handle(Channel::Green),
+            blue: handle(Channel::Blue),
+        })
+    }
+
+    /// Take the driver back
+    pub fn into_inner(self) -> LM3549<I2C> {
+        self.inner.into_inner().into_inner()
+    }
+}
+
+impl<I2C, E> SharedLM3549<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    /// Set master fader inside a critical section (Ctrl.mfe must be set)
+    pub fn set_fader_cs(&self, fade: u8) -> Result<(), Error<E>> {
+        self.with(|dev| dev.set_fader(fade))
+    }
+}
+
+impl<I2C> LM3549<I2C> {
+    /// Share the driver between interrupt handlers and the main loop
+    pub fn into_shared(self) -> SharedLM3549<I2C> {
+        SharedLM3549::new(self)
+    }
+}
+
+/// Per-channel handles of one bank, see [`SharedLM3549::split`]
+pub struct Channels<'a, I2C> {
+    /// Red channel
+    pub red: ChannelHandle<'a, I2C>,
+    /// Green channel
+    pub green: ChannelHandle<'a, I2C>,
+    /// Blue channel
+    pub blue: ChannelHandle<'a, I2C>,
+}
+
+/// Access to a single channel of a bank of a [`SharedLM3549`]
+pub struct ChannelHandle<'a, I2C> {
+    shared: &'a SharedLM3549<I2C>,
+    bank: Bank,
+    channel: Channel,
+}
+
+impl<'a, I2C> ChannelHandle<'a, I2C> {
+    /// Bank of the handle
+    pub fn bank(&self) -> Bank {
+        self.bank
+    }
+
+    /// Channel of the handle
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+}
+
+impl<'a, I2C, E> ChannelHandle<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    /// Write the current code of the channel
+    ///
+    /// Runs [`LM3549::write_channel`] in one critical section, so writes from other
+    /// handles cannot interleave with it.
+    pub fn set_current(&mut self, code: u16) -> Result<(), Error<E>> {
+        let (bank, channel) = (self.bank, self.channel);
+        self.shared
+            .with(|dev| dev.write_channel(bank, channel, code))
+    }
+
+    /// Read the current code of the channel
+    pub fn current(&mut self) -> Result<u16, Error<E>> {
+        let (bank, channel) = (self.bank, self.channel);
+        self.shared.with(|dev| dev.read_channel(bank, channel))
+    }
+}
+
+#[cfg(feature = "effects")]
+impl<'a, I2C, E> LedChannel for ChannelHandle<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    type Error = Error<E>;
+
+    fn set_level(&mut self, code: u16) -> Result<(), Error<E>> {
+        self.set_current(code)
+    }
+
+    fn max_level(&self) -> u16 {
+        MAX_CODE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+    use crate::{bank_channel_reg, Color, Register};
+
+    #[test]
+    fn bank_is_split_once() {
+        let (dev, _) = Model::driver();
+        let shared = dev.into_shared();
+        assert!(shared.split(Bank::B1).is_some());
+        assert!(shared.split(Bank::B1).is_none());
+        assert!(shared.split(Bank::B2).is_some());
+    }
+
+    #[test]
+    fn interleaved_handle_writes_do_not_stomp() {
+        let (dev, model) = Model::driver();
+        let shared = dev.into_shared();
+        let Channels {
+            mut red, mut green, ..
+        } = shared.split(Bank::B1).unwrap();
+
+        for i in 0..8u16 {
+            red.set_current(0x100 + i).unwrap();
+            green.set_current(0x300 - i).unwrap();
+            shared.set_fader_cs(i as u8).unwrap();
+        }
+
+        assert_eq!(red.current().unwrap(), 0x107);
+        assert_eq!(green.current().unwrap(), 0x2F9);
+        let bank = shared.with(|dev| dev.read_bank(Bank::B1)).unwrap();
+        assert_eq!(bank, Color::new(0x107, 0x2F9, 0));
+        assert_eq!(model.reg(Register::Fader), 7);
+
+        model.clear_ops();
+        red.set_current(0x123).unwrap();
+        green.set_current(0x045).unwrap();
+        let values = model.writes().into_iter().filter(|w| w.len() == 3);
+        let red_lsb = bank_channel_reg(Bank::B1, Channel::Red, false) as u8;
+        let green_lsb = bank_channel_reg(Bank::B1, Channel::Green, false) as u8;
+        assert!(values.eq([[red_lsb, 0x23, 0x01], [green_lsb, 0x45, 0x00]]
+            .iter()
+            .map(|w| w.to_vec())));
+    }
+
+    #[test]
+    fn with_and_set_fader_cs_reach_the_device() {
+        let (dev, model) = Model::driver();
+        let shared = dev.into_shared();
+        shared.set_fader_cs(0x42).unwrap();
+        assert_eq!(model.reg(Register::Fader), 0x42);
+        let fader = shared.with(|dev| dev.read(Register::Fader)).unwrap();
+        assert_eq!(fader, 0x42);
+        shared.with(|dev| dev.write(Register::User1, 7)).unwrap();
+        assert_eq!(model.reg(Register::User1), 7);
+        assert_eq!(shared.into_inner().address(), 0x36);
+    }
+
+    #[test]
+    #[cfg(feature = "effects")]
+    fn ramp_a_channel_handle() {
+        use crate::effects::ramp;
+        use embedded_hal_mock::delay::MockNoop;
+
+        let (dev, model) = Model::driver();
+        let shared = dev.into_shared();
+        let mut blue = shared.split(Bank::B2).unwrap().blue;
+        ramp(&mut blue, 0x100, 0x500, 4, 1, &mut MockNoop::new()).unwrap();
+        assert_eq!(blue.current().unwrap(), MAX_CODE);
+        let lsb = bank_channel_reg(Bank::B2, Channel::Blue, false) as u8;
+        let codes = model
+            .writes()
+            .into_iter()
+            .filter(|w| w.len() == 3 && w[0] == lsb)
+            .map(|w| u16::from_le_bytes([w[1], w[2]]));
+        assert!(codes.eq([0x1BF, 0x27F, 0x33F, MAX_CODE]));
+    }
+}
@@ -0,0 +1,306 @@
+//! Device lifecycle tracking
+
+use hal::blocking::i2c;
+
+use crate::{Error, Fault, Register, SoftStart, CONFIG_LEN, LM3549};
+
+/// Device lifecycle mode
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeviceMode {
+    /// Outputs disabled
+    Standby,
+    /// Outputs ramping up after enable
+    SoftStart,
+    /// Outputs running
+    Active,
+    /// A fault flag is active
+    Fault,
+}
+
+/// Change of [`DeviceMode`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Transition {
+    /// Mode before the change
+    pub from: DeviceMode,
+    /// Mode after the change
+    pub to: DeviceMode,
+}
+
+/// Tracks the device lifecycle from periodic fault polls
+///
+/// Times are milliseconds from any free-running tick counter, wrap-around is handled.
+#[derive(Copy, Clone, Debug)]
+pub struct Supervisor {
+    mode: DeviceMode,
+    soft_start_ms: u32,
+    since_ms: u32,
+}
+
+impl Supervisor {
+    /// Create a supervisor in standby for a device configured with `soft_start`
+    pub fn new(soft_start: SoftStart) -> Self {
+        Supervisor {
+            mode: DeviceMode::Standby,
+            soft_start_ms: soft_start.as_millis() as u32,
+            since_ms: 0,
+        }
+    }
+
+    /// Current mode
+    pub fn mode(&self) -> DeviceMode {
+        self.mode
+    }
+
+    /// Outputs were enabled at `now_ms`
+    pub fn enable(&mut self, now_ms: u32) -> Option<Transition> {
+        if self.mode != DeviceMode::Standby {
+            return None;
+        }
+        self.since_ms = now_ms;
+        self.change(DeviceMode::SoftStart)
+    }
+
+    /// Outputs were disabled
+    pub fn disable(&mut self) -> Option<Transition> {
+        self.change(DeviceMode::Standby)
+    }
+
+    /// Advance with a fault register value read at `now_ms`
+    pub fn update(&mut self, fault: &Fault, now_ms: u32) -> Option<Transition> {
+        match self.mode {
+            DeviceMode::Standby => None,
+            _ if fault.is_err() => self.change(DeviceMode::Fault),
+            DeviceMode::Fault => self.change(DeviceMode::Active),
+            DeviceMode::SoftStart if now_ms.wrapping_sub(self.since_ms) >= self.soft_start_ms => {
+                self.change(DeviceMode::Active)
+            }
+            _ => None,
+        }
+    }
+
+    /// Read the fault register and advance
+    pub fn poll<I2C, E>(
+        &mut self,
+        dev: &mut LM3549<I2C>,
+        now_ms: u32,
+    ) -> Result<Option<Transition>, Error<E>>
+    where
+        I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+    {
+        let fault = dev.get_fault()?;
+        Ok(self.update(&fault, now_ms))
+    }
+
+    fn change(&mut self, to: DeviceMode) -> Option<Transition> {
+        let from = self.mode;
+        if from == to {
+            return None;
+        }
+        self.mode = to;
+        Some(Transition { from, to })
+    }
+}
+
+/// Device health summary of [`LM3549::health_check`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Health {
+    /// The device answered the fault register read
+    pub responding: bool,
+    /// Fault register read by the check, zero if the device did not respond
+    ///
+    /// The read clears latched faults, so a later [`LM3549::get_fault`] does not report
+    /// them again.
+    pub faults: Fault,
+    /// Whether the device matches the golden configuration, `None` without one or if the
+    /// device did not respond
+    pub config_ok: Option<bool>,
+    /// The last bus transaction before the check failed, or the fault read of the check
+    ///
+    /// A snapshot of the bus, not a mode: the next successful transaction clears it.
+    pub degraded: bool,
+}
+
+impl<I2C, E> LM3549<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::Read<Error = E>,
+{
+    /// Summarize the device health, e.g. for a watchdog task
+    ///
+    /// Reads the fault register and, with a golden configuration installed by
+    /// [`LM3549::set_golden_config`], the configuration registers: at most two transactions.
+    /// BankSel and Fader change at runtime and are not compared. A failing fault read is
+    /// reported as not responding; a bus error after the device answered is returned.
+    pub fn health_check(&mut self) -> Result<Health, Error<E>> {
+        let degraded = self.bus_failures > 0;
+        let faults = match self.get_fault() {
+            Ok(fault) => fault,
+            Err(Error::I2c(..)) => {
+                return Ok(Health {
+                    responding: false,
+                    faults: Fault(0),
+                    config_ok: None,
+                    degraded: true,
+                })
+            }
+            Err(e) => return Err(e),
+        };
+        let config_ok = match self.golden {
+            Some(golden) => {
+                let expected = self.encode_config(&golden)?;
+                let mut actual = [0u8; CONFIG_LEN];
+                self.read_registers(Register::BankSel, &mut actual)?;
+                let runtime = [Register::BankSel as usize, Register::Fader as usize];
+                let ok = expected
+                    .iter()
+                    .zip(actual.iter())
+                    .enumerate()
+                    .all(|(i, (e, a))| runtime.contains(&i) || e == a);
+                Some(ok)
+            }
+            None => None,
+        };
+        Ok(Health {
+            responding: true,
+            faults,
+            config_ok,
+            degraded,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Model;
+    use crate::{Color, Config, Ctrl};
+
+    fn golden() -> Config {
+        Config {
+            banks: [
+                Color {
+                    r: 0x200,
+                    g: 0x100,
+                    b: 0x080,
+                },
+                Color::default(),
+                Color::default(),
+            ],
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn health_check_healthy() {
+        let (mut dev, _) = Model::driver();
+        dev.apply(&golden()).unwrap();
+        dev.set_golden_config(golden());
+        assert_eq!(
+            dev.health_check().unwrap(),
+            Health {
+                responding: true,
+                faults: Fault(0),
+                config_ok: Some(true),
+                degraded: false,
+            }
+        );
+    }
+
+    #[test]
+    fn health_check_faulted() {
+        let (mut dev, model) = Model::driver();
+        model.set_reg(Register::Fault, 0x06);
+        let health = dev.health_check().unwrap();
+        assert!(health.responding);
+        assert!(health.faults.uvlo() && health.faults.tsd());
+        assert_eq!(health.config_ok, None);
+    }
+
+    #[test]
+    fn health_check_misconfigured() {
+        let (mut dev, model) = Model::driver();
+        dev.apply(&golden()).unwrap();
+        dev.set_golden_config(golden());
+        model.set_reg(Register::Ctrl, Ctrl::default().0 ^ 0x01);
+        model.set_reg(Register::Fader, 0x10);
+        assert_eq!(dev.health_check().unwrap().config_ok, Some(false));
+    }
+
+    #[test]
+    fn health_check_degraded() {
+        let (mut dev, model) = Model::driver();
+        model.fail_in(0);
+        assert!(dev.set_fader(0x10).is_err());
+        let health = dev.health_check().unwrap();
+        assert!(health.responding && health.degraded);
+        assert!(!dev.health_check().unwrap().degraded);
+
+        model.fail_in(1);
+        let health = dev.health_check().unwrap();
+        assert!(!health.responding && health.degraded);
+    }
+
+    #[test]
+    fn supervisor_lifecycle() {
+        let (mut dev, model) = Model::driver();
+        let mut sup = Supervisor::new(SoftStart::MS500);
+        assert_eq!(sup.mode(), DeviceMode::Standby);
+        assert_eq!(sup.poll(&mut dev, 0).unwrap(), None);
+
+        let soft_start = Transition {
+            from: DeviceMode::Standby,
+            to: DeviceMode::SoftStart,
+        };
+        assert_eq!(sup.enable(100), Some(soft_start));
+        assert_eq!(sup.enable(150), None);
+        assert_eq!(sup.poll(&mut dev, 599).unwrap(), None);
+        assert_eq!(
+            sup.poll(&mut dev, 600).unwrap(),
+            Some(Transition {
+                from: DeviceMode::SoftStart,
+                to: DeviceMode::Active,
+            })
+        );
+
+        model.set_reg(Register::Fault, 0x02);
+        assert_eq!(
+            sup.poll(&mut dev, 700).unwrap(),
+            Some(Transition {
+                from: DeviceMode::Active,
+                to: DeviceMode::Fault,
+            })
+        );
+        assert_eq!(sup.poll(&mut dev, 710).unwrap(), None);
+
+        model.set_reg(Register::Fault, 0x00);
+        assert_eq!(
+            sup.poll(&mut dev, 720).unwrap(),
+            Some(Transition {
+                from: DeviceMode::Fault,
+                to: DeviceMode::Active,
+            })
+        );
+        assert_eq!(
+            sup.disable(),
+            Some(Transition {
+                from: DeviceMode::Active,
+                to: DeviceMode::Standby,
+            })
+        );
+    }
+
+    #[test]
+    fn supervisor_soft_start_across_wrap_around() {
+        let mut sup = Supervisor::new(SoftStart::MS1000);
+        sup.enable(u32::MAX - 100);
+        assert_eq!(sup.update(&Fault(0), 500), None);
+        assert!(sup.update(&Fault(0), 900).is_some());
+        assert_eq!(sup.mode(), DeviceMode::Active);
+    }
+
+    #[test]
+    fn supervisor_fault_during_soft_start() {
+        let mut sup = Supervisor::new(SoftStart::None);
+        sup.enable(0);
+        assert_eq!(sup.update(&Fault(0x01), 0).unwrap().to, DeviceMode::Fault);
+    }
+}
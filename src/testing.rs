@@ -0,0 +1,227 @@
+//! Register model of the LM3549 for unit tests
+
+// Not every test module uses every helper
+#![allow(dead_code)]
+
+extern crate std;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use hal::blocking::i2c;
+
+use crate::{Classify, ErrorClass, Register, LM3549};
+
+/// Bus error of the model, of the class set in [`State::error_class`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BusError(pub ErrorClass);
+
+impl std::fmt::Display for BusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?} bus error", self.0)
+    }
+}
+
+impl std::error::Error for BusError {}
+
+impl Classify for BusError {
+    fn classify(&self) -> ErrorClass {
+        self.0
+    }
+}
+
+/// Transaction seen by the model
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Op {
+    /// Write to an address: register pointer and data
+    Write(u8, Vec<u8>),
+    /// Read from an address: data returned
+    Read(u8, Vec<u8>),
+}
+
+/// State shared between the model handed to the driver and the test
+#[derive(Debug)]
+pub(crate) struct State {
+    /// Register file, indexed by address
+    pub regs: [u8; 0x41],
+    /// Contents of the EEPROM, BankSel to FaultMask
+    pub eeprom: [u8; 0x17],
+    /// Every transaction, failed ones included
+    pub ops: Vec<Op>,
+    /// Number of `Transactional::exec` calls, each one transaction however many ops
+    pub execs: usize,
+    /// Fail the transactions with these indices into `ops`
+    pub fail: Vec<usize>,
+    /// Class of the errors of failing transactions
+    pub error_class: ErrorClass,
+    /// EEPROM operations never report ready
+    pub eeprom_stuck: bool,
+    /// EEPROM cell at this address keeps its old value when programmed
+    pub eeprom_dead: Option<u8>,
+    pointer: u8,
+}
+
+/// LM3549 register file behind a fake I2C bus, cloned handles share their state
+#[derive(Clone, Debug)]
+pub(crate) struct Model(pub Rc<RefCell<State>>);
+
+impl Model {
+    /// Model with every register zero
+    pub fn new() -> Self {
+        Model(Rc::new(RefCell::new(State {
+            regs: [0; 0x41],
+            eeprom: [0; 0x17],
+            ops: Vec::new(),
+            execs: 0,
+            fail: Vec::new(),
+            error_class: ErrorClass::Transient,
+            eeprom_stuck: false,
+            eeprom_dead: None,
+            pointer: 0,
+        })))
+    }
+
+    /// Driver at the default address on a new model, with a handle to the model
+    pub fn driver() -> (LM3549<Model>, Model) {
+        let model = Model::new();
+        (LM3549::new(model.clone()), model)
+    }
+
+    /// Current value of a register
+    pub fn reg(&self, register: Register) -> u8 {
+        self.0.borrow().regs[register as usize]
+    }
+
+    /// Set a register without a transaction
+    pub fn set_reg(&self, register: Register, value: u8) {
+        self.0.borrow_mut().regs[register as usize] = value;
+    }
+
+    /// Transactions so far
+    pub fn ops(&self) -> Vec<Op> {
+        self.0.borrow().ops.clone()
+    }
+
+    /// Data of the write transactions so far, register pointer first
+    pub fn writes(&self) -> Vec<Vec<u8>> {
+        self.ops()
+            .into_iter()
+            .filter_map(|op| match op {
+                Op::Write(_, data) => Some(data),
+                Op::Read(..) => None,
+            })
+            .collect()
+    }
+
+    /// Forget the transactions so far
+    pub fn clear_ops(&self) {
+        self.0.borrow_mut().ops.clear();
+    }
+
+    /// Fail the `n`th transaction from now, counting from zero
+    pub fn fail_in(&self, n: usize) {
+        let mut s = self.0.borrow_mut();
+        let at = s.ops.len() + n;
+        s.fail.push(at);
+    }
+
+    /// Power cycle the device: every register back to zero
+    pub fn reset(&self) {
+        let mut s = self.0.borrow_mut();
+        s.regs = [0; 0x41];
+        s.pointer = 0;
+    }
+}
+
+impl State {
+    /// Log a transaction, `Err` if it is set up to fail
+    fn log(&mut self, op: Op) -> Result<(), BusError> {
+        let failed = self.fail.contains(&self.ops.len());
+        self.ops.push(op);
+        if failed {
+            Err(BusError(self.error_class))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), BusError> {
+        self.log(Op::Write(address, bytes.to_vec()))?;
+        let (&start, data) = match bytes.split_first() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        self.pointer = start;
+        for &value in data {
+            self.store(self.pointer, value);
+            self.pointer = self.pointer.wrapping_add(1);
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), BusError> {
+        for b in buffer.iter_mut() {
+            *b = self.load(self.pointer);
+            self.pointer = self.pointer.wrapping_add(1);
+        }
+        self.log(Op::Read(address, buffer.to_vec()))
+    }
+
+    fn store(&mut self, addr: u8, value: u8) {
+        let eeprom = Register::EepromCtrl as u8;
+        if addr == eeprom {
+            let len = self.eeprom.len();
+            if value & 0x02 != 0 {
+                let mut regs = self.regs;
+                if let Some(dead) = self.eeprom_dead {
+                    regs[dead as usize] = self.eeprom[dead as usize];
+                }
+                self.eeprom.copy_from_slice(&regs[..len]);
+            }
+            if value & 0x01 != 0 {
+                let eeprom = self.eeprom;
+                self.regs[..len].copy_from_slice(&eeprom);
+            }
+            self.regs[addr as usize] = if self.eeprom_stuck { 0x00 } else { 0x04 };
+        } else if let Some(r) = self.regs.get_mut(addr as usize) {
+            *r = value;
+        }
+    }
+
+    fn load(&self, addr: u8) -> u8 {
+        self.regs.get(addr as usize).copied().unwrap_or(0)
+    }
+}
+
+impl i2c::Write for Model {
+    type Error = BusError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), BusError> {
+        self.0.borrow_mut().write(address, bytes)
+    }
+}
+
+impl i2c::Read for Model {
+    type Error = BusError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), BusError> {
+        self.0.borrow_mut().read(address, buffer)
+    }
+}
+
+impl i2c::Transactional for Model {
+    type Error = BusError;
+
+    fn exec(&mut self, address: u8, operations: &mut [i2c::Operation]) -> Result<(), BusError> {
+        let mut s = self.0.borrow_mut();
+        s.execs += 1;
+        for op in operations.iter_mut() {
+            match op {
+                i2c::Operation::Write(bytes) => s.write(address, bytes)?,
+                i2c::Operation::Read(buffer) => s.read(address, buffer)?,
+            }
+        }
+        Ok(())
+    }
+}
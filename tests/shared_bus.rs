@@ -0,0 +1,70 @@
+//! Two drivers and another device on one embedded-hal 1.0 bus through `RefCellDevice`
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+
+use embedded_hal_1::i2c::{ErrorType, I2c, Operation};
+use embedded_hal_bus::i2c::RefCellDevice;
+use lm3549::{Bank, BankCurrents, LM3549};
+
+#[derive(Debug, PartialEq)]
+enum Op {
+    Write(u8, Vec<u8>),
+    Read(u8, usize),
+}
+
+/// Bus recording every transaction, reads return zeros
+#[derive(Default)]
+struct RecordingBus(Vec<Op>);
+
+impl ErrorType for RecordingBus {
+    type Error = Infallible;
+}
+
+impl I2c for RecordingBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Infallible> {
+        for op in operations {
+            match op {
+                Operation::Write(bytes) => self.0.push(Op::Write(address, bytes.to_vec())),
+                Operation::Read(buf) => {
+                    buf.iter_mut().for_each(|b| *b = 0);
+                    self.0.push(Op::Read(address, buf.len()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn interleaved_transactions() {
+    let bus = RefCell::new(RecordingBus::default());
+
+    let mut front = LM3549::new_eh1(RefCellDevice::new(&bus));
+    let mut back = LM3549::new_eh1(RefCellDevice::new(&bus));
+    back.set_address(0x37).unwrap();
+    let mut other = RefCellDevice::new(&bus);
+
+    front
+        .write_bank_currents(Bank::B0, BankCurrents::new(0x3FF, 0, 0))
+        .unwrap();
+    other.write(0x50, &[0x00, 0xAA]).unwrap();
+    back.write_bank_currents(Bank::B1, BankCurrents::new(0, 0, 0x3FF))
+        .unwrap();
+    assert_eq!(front.read(lm3549::Register::Ctrl).unwrap(), 0);
+
+    assert_eq!(
+        bus.into_inner().0,
+        vec![
+            Op::Write(0x36, vec![0x01, 0xFF, 0x03, 0x00, 0x00, 0x00, 0x00]),
+            Op::Write(0x50, vec![0x00, 0xAA]),
+            Op::Write(0x37, vec![0x07, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x03]),
+            Op::Write(0x36, vec![0x14]),
+            Op::Read(0x36, 1),
+        ]
+    );
+}
@@ -0,0 +1,5 @@
+const BOOT_COLOR: lm3549::BankCurrents = lm3549::BankCurrents::new(0x200, 0x800, 0x0F0);
+
+fn main() {
+    let _ = BOOT_COLOR;
+}
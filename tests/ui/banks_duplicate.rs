@@ -0,0 +1,7 @@
+lm3549::lm3549_banks! {
+    B0 => off,
+    B1 => rgb8(255, 120, 0),
+    B0 => (0x100, 0, 0),
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+lm3549::lm3549_banks! {
+    B0 => (0x400, 0, 0),
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+lm3549::lm3549_banks! {
+    B0 => (300 mA, 9000 mA, 0 mA),
+}
+
+fn main() {}